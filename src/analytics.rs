@@ -0,0 +1,312 @@
+//! What a year looks like by the fits: solstices and equinoxes from the
+//! solar calculator, and from the fitted curves the longest and shortest
+//! day, the earliest and latest sunrise and sunset (which fall weeks away
+//! from the solstices) and how fast the daylight changes. A sine peaks at
+//! the solstices, so in its place the extremes come from the physical
+//! model, or a Fourier series where that cannot be fitted.
+
+use std::cell::OnceCell;
+use std::fmt;
+
+use serde::Serialize;
+
+use crate::day::{days_in_month, days_in_year, Dataset, Quantity};
+use crate::fit::{Fitment, FourierFitment, PhysicalFit, YEAR};
+use crate::format::clock;
+use crate::solar;
+
+/// Harmonics of the Fourier series standing in for a sine.
+const HARMONICS: usize = 2;
+
+/// Analytics for each year a dataset spans.
+#[derive(Debug, Serialize)]
+pub struct Analytics {
+    pub label: String,
+    pub years: Vec<YearAnalytics>,
+}
+
+/// A day on which a fitted curve peaks.
+#[derive(Debug, Serialize)]
+pub struct Extreme {
+    pub date: String,
+    /// Day number on the dataset's axis
+    pub day: u64,
+    /// Hours, or minutes per day for the rate of change
+    pub value: f64,
+    /// The curve it was read off, e.g. "physical" in place of a sine
+    pub model: String,
+}
+
+#[derive(Debug, Serialize)]
+pub struct YearAnalytics {
+    pub year: u64,
+    /// Instants as "YYYY-MM-DD HH:MM" UTC
+    pub march_equinox: String,
+    pub june_solstice: String,
+    pub september_equinox: String,
+    pub december_solstice: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub longest_day: Option<Extreme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub shortest_day: Option<Extreme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earliest_sunrise: Option<Extreme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_sunrise: Option<Extreme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub earliest_sunset: Option<Extreme>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub latest_sunset: Option<Extreme>,
+    /// Largest day-to-day gain in day length, minutes per day
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fastest_lengthening: Option<Extreme>,
+    /// Largest day-to-day loss in day length, minutes per day (negative)
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub fastest_shortening: Option<Extreme>,
+}
+
+impl Analytics {
+    /// From the dataset's fits (the first of each quantity), day by day
+    /// over every year it spans; no years without days.
+    pub fn compute(dataset: &Dataset) -> Self {
+        let mut years = Vec::new();
+        if let Some(last_year) = dataset.days.iter().map(|day| day.y).max() {
+            let physical = OnceCell::new();
+            let mut first_day = 1;
+            for year in dataset.start_year..=last_year {
+                let days: Vec<u64> = (first_day..first_day + days_in_year(year)).collect();
+                first_day += days_in_year(year);
+                years.push(YearAnalytics::compute(dataset, &physical, year, &days));
+            }
+        }
+        Analytics {
+            label: dataset.label.clone(),
+            years,
+        }
+    }
+}
+
+/// The curve of `quantity` to take extremes from: its fit, unless that is
+/// a sine, whose extremes sit at the solstices.
+fn extremes_curve(
+    dataset: &Dataset,
+    physical: &OnceCell<Option<PhysicalFit>>,
+    quantity: Quantity,
+) -> Option<Fitment> {
+    let fitment = dataset.fit(quantity)?;
+    if !matches!(fitment, Fitment::Sine(_)) {
+        return Some(fitment.clone());
+    }
+    let physical = physical.get_or_init(|| {
+        let utc_offset = dataset
+            .location
+            .as_ref()
+            .and_then(|location| location.utc_offset);
+        PhysicalFit::fit(&dataset.days, utc_offset)
+    });
+    if let Some(fit) = physical.as_ref() {
+        if PhysicalFit::QUANTITIES.contains(&quantity) {
+            return Some(Fitment::Physical(quantity, fit.clone()));
+        }
+    }
+    let points: Vec<(f64, f64)> = dataset
+        .series(quantity)
+        .iter()
+        .map(|&(value, day)| (day as f64, value))
+        .collect();
+    match FourierFitment::fit(&points, YEAR, HARMONICS) {
+        Some(fitment) => Some(Fitment::Fourier(fitment)),
+        None => Some(fitment.clone()),
+    }
+}
+
+impl YearAnalytics {
+    fn compute(
+        dataset: &Dataset,
+        physical: &OnceCell<Option<PhysicalFit>>,
+        year: u64,
+        days: &[u64],
+    ) -> Self {
+        let [march_equinox, june_solstice, september_equinox, december_solstice] =
+            seasons(year).map(|offset| instant(year, offset));
+        let fitted_extremes = |fitment: &Fitment, value: fn(&Fitment, f64) -> f64| {
+            let values: Vec<(f64, u64)> = days
+                .iter()
+                .map(|&day| (value(fitment, day as f64), day))
+                .collect();
+            extremes(dataset, &fitment.model(), &values)
+        };
+        let hours = |fitment: &Fitment, x: f64| fitment.eval(x);
+        let curve = |quantity| extremes_curve(dataset, physical, quantity);
+        let length = curve(Quantity::DayLength);
+        let (shortest_day, longest_day) = length
+            .as_ref()
+            .and_then(|fitment| fitted_extremes(fitment, hours))
+            .unzip();
+        let (earliest_sunrise, latest_sunrise) = curve(Quantity::Sunrise)
+            .and_then(|fitment| fitted_extremes(&fitment, hours))
+            .unzip();
+        let (earliest_sunset, latest_sunset) = curve(Quantity::Sunset)
+            .and_then(|fitment| fitted_extremes(&fitment, hours))
+            .unzip();
+        // minutes per day
+        let rate = |fitment: &Fitment, x: f64| (fitment.eval(x + 1.0) - fitment.eval(x)) * 60.0;
+        let (fastest_shortening, fastest_lengthening) = length
+            .as_ref()
+            .and_then(|fitment| fitted_extremes(fitment, rate))
+            .unzip();
+
+        YearAnalytics {
+            year,
+            march_equinox,
+            june_solstice,
+            september_equinox,
+            december_solstice,
+            longest_day,
+            shortest_day,
+            earliest_sunrise,
+            latest_sunrise,
+            earliest_sunset,
+            latest_sunset,
+            fastest_lengthening,
+            fastest_shortening,
+        }
+    }
+}
+
+/// The (least, greatest) of `(value, day)` samples of the `model` curve;
+/// `None` without samples.
+fn extremes(dataset: &Dataset, model: &str, values: &[(f64, u64)]) -> Option<(Extreme, Extreme)> {
+    let extreme = |&(value, day): &(f64, u64)| {
+        let (y, m, d) = dataset.date(day);
+        Extreme {
+            date: format!("{:04}-{:02}-{:02}", y, m, d),
+            day,
+            value,
+            model: model.to_string(),
+        }
+    };
+    let least = values.iter().min_by(|a, b| a.0.total_cmp(&b.0))?;
+    let greatest = values.iter().max_by(|a, b| a.0.total_cmp(&b.0))?;
+    Some((extreme(least), extreme(greatest)))
+}
+
+/// Days after 0h UT on 1 January of the March equinox, June solstice,
+/// September equinox and December solstice: where the declination changes
+/// sign, interpolated linearly, and where it peaks, through a parabola.
+fn seasons(year: u64) -> [f64; 4] {
+    let new_year = solar::julian_day(year, 1, 1);
+    let declination: Vec<f64> = (0..=days_in_year(year))
+        .map(|day| solar::position(new_year + day as f64).declination)
+        .collect();
+
+    let crossing = |rising: bool| {
+        (0..declination.len() - 1)
+            .find(|&day| {
+                let (a, b) = (declination[day], declination[day + 1]);
+                if rising {
+                    a < 0.0 && b >= 0.0
+                } else {
+                    a > 0.0 && b <= 0.0
+                }
+            })
+            .map_or(0.0, |day| {
+                let (a, b) = (declination[day], declination[day + 1]);
+                day as f64 + a / (a - b)
+            })
+    };
+    let peak = |greatest: bool| {
+        let day = (1..declination.len() - 1)
+            .max_by(|&a, &b| {
+                let order = declination[a].total_cmp(&declination[b]);
+                if greatest {
+                    order
+                } else {
+                    order.reverse()
+                }
+            })
+            .unwrap_or(1);
+        let (a, b, c) = (declination[day - 1], declination[day], declination[day + 1]);
+        day as f64 + (a - c) / (2.0 * (a - 2.0 * b + c))
+    };
+    [crossing(true), peak(true), crossing(false), peak(false)]
+}
+
+/// "2024-03-20 03:06" for days after 0h UT on 1 January of `year`.
+fn instant(year: u64, offset: f64) -> String {
+    let minutes = (offset * 1440.0).round() as u64;
+    let (mut month, mut day) = (1, minutes / 1440 + 1);
+    while day > days_in_month(year, month) {
+        day -= days_in_month(year, month);
+        month += 1;
+    }
+    format!(
+        "{:04}-{:02}-{:02} {}",
+        year,
+        month,
+        day,
+        clock((minutes % 1440) as f64 / 60.0)
+    )
+}
+
+impl fmt::Display for Analytics {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for year in &self.years {
+            writeln!(f, "{} {}:", self.label, year.year)?;
+            writeln!(
+                f,
+                "  March equinox {} UTC, June solstice {} UTC",
+                year.march_equinox, year.june_solstice
+            )?;
+            writeln!(
+                f,
+                "  September equinox {} UTC, December solstice {} UTC",
+                year.september_equinox, year.december_solstice
+            )?;
+            let pairs = [
+                (
+                    "Longest day",
+                    &year.longest_day,
+                    "shortest day",
+                    &year.shortest_day,
+                ),
+                (
+                    "Earliest sunrise",
+                    &year.earliest_sunrise,
+                    "latest sunrise",
+                    &year.latest_sunrise,
+                ),
+                (
+                    "Earliest sunset",
+                    &year.earliest_sunset,
+                    "latest sunset",
+                    &year.latest_sunset,
+                ),
+            ];
+            for (first_name, first, second_name, second) in pairs {
+                if let (Some(first), Some(second)) = (first, second) {
+                    writeln!(
+                        f,
+                        "  {} {} {}, {} {} {}, from the {} fit",
+                        first_name,
+                        first.date,
+                        clock(first.value),
+                        second_name,
+                        second.date,
+                        clock(second.value),
+                        first.model
+                    )?;
+                }
+            }
+            if let (Some(gain), Some(loss)) = (&year.fastest_lengthening, &year.fastest_shortening)
+            {
+                writeln!(
+                    f,
+                    "  Daylight changes by up to {:+.1} min/day ({}) and {:+.1} min/day ({}), from the {} fit",
+                    gain.value, gain.date, loss.value, loss.date, gain.model
+                )?;
+            }
+        }
+        Ok(())
+    }
+}
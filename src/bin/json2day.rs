@@ -1,500 +1,717 @@
 use clap::Parser;
-use serde::{Deserialize, Serialize};
 use std::fs;
-
-use serde_this_or_that::{as_f64, as_u64};
-
-use plotters::prelude::*;
+use std::io::IsTerminal;
+
+use sun::analytics::Analytics;
+use sun::day::{Dataset, Quantity, Resample, Unit};
+use sun::eot::EquationOfTime;
+use sun::fit::{
+    Candidate, CrossValidation, Fitment, FourierFitment, Joint, JointFitment, Loss, Model,
+    ModelChoice, PhysicalFit, RobustFit, SineFitment, SineIntervals, SplineFitment, MAX_HARMONICS,
+    YEAR,
+};
+use sun::html;
+use sun::place;
+use sun::plot::{self, Chart, Layout};
+use sun::term;
+use sun::validate::{Report, ReportFormat};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Format {
+    /// Static SVG image
+    Svg,
+    /// Self-contained interactive HTML page
+    Html,
+}
 
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// Input JSON file
-    #[arg(short, long, env = "INPUT", value_name = "INPUT")]
-    input: std::path::PathBuf,
+    /// Input JSON file; repeat to compare several locations on one chart
+    #[arg(short, long, env = "INPUT", value_name = "INPUT", required = true)]
+    input: Vec<std::path::PathBuf>,
 
     /// Output image
-    #[arg(short, long, env = "OUTPUT", value_name = "OUTPUT")]
-    output: std::path::PathBuf,
-
-    /// Transformed output JSON file
-    #[arg(short, long, env = "TRANSFORMED", value_name = "TRANSFORMED")]
-    transformed: Option<std::path::PathBuf>,
+    #[arg(
+        short,
+        long,
+        env = "OUTPUT",
+        value_name = "OUTPUT",
+        required_unless_present_any = ["terminal", "infer", "validate"]
+    )]
+    output: Option<std::path::PathBuf>,
 
-    /// Label
-    #[arg(short, long, env = "LABEL", value_name = "LABEL")]
-    label: Option<String>,
-}
+    /// Preview the chart in the terminal as braille
+    #[arg(long)]
+    terminal: bool,
 
-#[derive(Serialize, Deserialize, Debug, Clone)]
-#[serde(untagged)]
-pub enum NumOrStr {
-    Str(String),
-    #[serde(deserialize_with = "as_f64")]
-    Num(f64),
-}
+    /// Output format (defaults to html for .html/.htm outputs, svg otherwise)
+    #[arg(long, value_enum)]
+    format: Option<Format>,
 
-impl NumOrStr {
-    pub fn as_num(&self) -> Self {
-        match self {
-            NumOrStr::Str(ref s) => {
-                let parts: Vec<&str> = s.split(|c| ":,".contains(c)).collect();
-                let h = parts[0].parse::<f64>().unwrap();
-                let m = parts[1].parse::<f64>().unwrap();
-                NumOrStr::Num(h + m / 60.0)
-            }
-            _ => self.clone(),
-        }
-    }
+    /// Transformed output JSON file; repeat once per input
+    #[arg(short, long, env = "TRANSFORMED", value_name = "TRANSFORMED")]
+    transformed: Vec<std::path::PathBuf>,
 
-    pub fn as_str(&self) -> Self {
-        match self {
-            NumOrStr::Num(n) => {
-                NumOrStr::Str(format!("{}:{}", (*n as u64), ((n.fract() * 60.0) as u64)))
-            }
-            _ => self.clone(),
-        }
-    }
+    /// Fill the transformed output with a row for every day, interpolating
+    /// the days between observations (marked "Interpolated")
+    #[arg(long, value_enum, value_name = "METHOD", requires = "transformed")]
+    resample: Option<Resample>,
 
-    pub fn get_num(&self) -> f64 {
-        match self {
-            NumOrStr::Num(n) => *n,
-            _ => self.as_num().get_num(),
-        }
-    }
-}
+    /// Add the day-to-day change of the fitted day length, sunrise and
+    /// sunset (minutes/day) to the transformed output
+    #[arg(long, requires = "transformed")]
+    change_rates: bool,
 
-impl std::fmt::Display for NumOrStr {
-    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
-        match self {
-            NumOrStr::Str(s) => write!(f, "{}", s),
-            NumOrStr::Num(n) => write!(f, "{}:{}", (*n as u64), ((n.fract() * 60.0) as u64)),
-        }
-    }
-}
+    /// Shade the 95% prediction band around each sine fit
+    #[arg(long)]
+    prediction_band: bool,
 
-#[derive(Serialize, Deserialize, Debug)]
-struct Day {
-    #[serde(deserialize_with = "as_u64")]
-    y: u64,
-    #[serde(deserialize_with = "as_u64")]
-    m: u64,
-    #[serde(deserialize_with = "as_u64")]
-    d: u64,
-    #[serde(deserialize_with = "as_u64")]
-    yday: u64,
-    srise: Option<NumOrStr>,
-    sset: Option<NumOrStr>,
-    solnoon: Option<NumOrStr>,
-    daylen: Option<NumOrStr>,
-}
+    /// Leave the values flagged as outliers by --robust out of the
+    /// transformed output
+    #[arg(long, requires_all = ["robust", "transformed"])]
+    exclude_outliers: bool,
 
-#[derive(Serialize, Debug)]
-struct XDay {
-    #[serde(rename(serialize = "Day Number"))]
-    yday: u64,
-    #[serde(
-        rename(serialize = "Sunrise Hour"),
-        skip_serializing_if = "Option::is_none"
+    /// Label; repeat once per input (defaults to the location in the input,
+    /// or the input file name)
+    #[arg(short, long, env = "LABEL", value_name = "LABEL")]
+    label: Vec<String>,
+
+    /// Chart to render
+    #[arg(long, value_enum, default_value_t = Chart::Line)]
+    chart: Chart,
+
+    /// Observer position as LAT,LON (degrees, east positive) to compute
+    /// missing rise/set azimuths and noon altitudes; repeat once per input
+    #[arg(long, value_name = "LAT,LON", allow_hyphen_values = true, value_parser = place::parse_coords)]
+    coords: Vec<(f64, f64)>,
+
+    /// Model fitted to each quantity (sine, physical, spline, fourier or
+    /// auto), or
+    /// QUANTITY=MODEL for one quantity (e.g. solnoon=spline); repeat to draw
+    /// further models dashed for comparison
+    #[arg(
+        long,
+        value_name = "[QUANTITY=]MODEL",
+        default_value = "sine",
+        value_parser = ModelChoice::parse
     )]
-    srise: Option<NumOrStr>,
-    #[serde(
-        rename(serialize = "Sunset Hour"),
-        skip_serializing_if = "Option::is_none"
+    model: Vec<ModelChoice>,
+
+    /// Harmonics of the fourier model
+    #[arg(long, default_value_t = 2, value_parser = clap::value_parser!(u64).range(1..=MAX_HARMONICS as u64))]
+    harmonics: u64,
+
+    /// Print each model's cross-validated error per quantity (done anyway
+    /// for --model auto)
+    #[arg(long)]
+    cross_validate: bool,
+
+    /// Folds for cross-validation (default: leave-one-out)
+    #[arg(long, value_parser = clap::value_parser!(u64).range(2..))]
+    folds: Option<u64>,
+
+    /// Fit solar noon and day length and derive sunrise and sunset from
+    /// them (or the other way round, rise-set), so the four fits agree
+    #[arg(
+        long,
+        value_enum,
+        value_name = "PAIR",
+        num_args = 0..=1,
+        default_missing_value = "noon-length"
     )]
-    sset: Option<NumOrStr>,
-    #[serde(
-        rename(serialize = "Daylight Length"),
-        skip_serializing_if = "Option::is_none"
+    joint: Option<Joint>,
+
+    /// UTC offset (hours) of the input's standard time, placing the physical
+    /// model in longitude; repeat once per input (defaults to the location
+    /// in the input)
+    #[arg(long, value_name = "HOURS", allow_hyphen_values = true)]
+    utc_offset: Vec<f64>,
+
+    /// Estimate where each input was observed, with a 95% confidence
+    /// region, and check the claimed location (the input's header or
+    /// --coords) against it; exits 1 if one falls outside
+    #[arg(long)]
+    infer: bool,
+
+    /// Check each input for unparsable records, duplicate, out-of-order or
+    /// impossible dates, times out of range and inconsistent fields, and
+    /// print a report; exits 1 on errors
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "human"
     )]
-    daylen: Option<NumOrStr>,
-    #[serde(
-        rename(serialize = "Solar Noon Time"),
-        skip_serializing_if = "Option::is_none"
+    validate: Option<ReportFormat>,
+
+    /// Report solstices, equinoxes, the longest and shortest day, the
+    /// earliest and latest sunrise and sunset and the fastest change in
+    /// daylight, from the fits
+    #[arg(
+        long,
+        value_enum,
+        value_name = "FORMAT",
+        num_args = 0..=1,
+        default_missing_value = "human"
     )]
-    solnoon: Option<NumOrStr>,
+    analytics: Option<ReportFormat>,
+
+    /// Fit the sines by robust regression instead of from the extremes, and
+    /// flag outlying samples in the report and chart
+    #[arg(
+        long,
+        value_enum,
+        value_name = "LOSS",
+        num_args = 0..=1,
+        default_missing_value = "tukey"
+    )]
+    robust: Option<Loss>,
+
+    /// How several inputs share the line chart
+    #[arg(long, value_enum, default_value_t = Layout::Overlay)]
+    layout: Layout,
 }
 
-fn main() {
-    let args = Args::parse();
+/// Fit the physical model, comparing the position it infers with the one
+/// claimed in the input.
+fn fit_physical(dataset: &Dataset, utc_offset: Option<f64>) -> Option<PhysicalFit> {
+    let Some(fit) = PhysicalFit::fit(&dataset.days, utc_offset) else {
+        eprintln!("Too few days with sunrise and sunset for the physical model");
+        return None;
+    };
+    println!("Physical fit: {}", fit);
+
+    if let Some(location) = &dataset.location {
+        let latitude_error = fit.latitude - location.lat as f64;
+        match fit.longitude() {
+            Some(longitude) => println!(
+                "Claimed {}: latitude off by {:+.2}°, longitude by {:+.2}°",
+                location.position(),
+                latitude_error,
+                longitude - location.lon as f64
+            ),
+            None => println!(
+                "Claimed {}: latitude off by {:+.2}°",
+                location.position(),
+                latitude_error
+            ),
+        }
+        if latitude_error.abs() > fit.region().0 {
+            eprintln!(
+                "Warning: {} does not look like data for {}",
+                dataset.label,
+                location.full_name()
+            );
+        }
+    }
+    println!("------------------------------");
 
-    let input = args.input;
+    Some(fit)
+}
 
-    let input = fs::read_to_string(&input).expect("Unable to read file");
-    let mut data: Vec<Day> = serde_json::from_str(&input).expect("Unable to parse JSON");
+/// Replace the fits of the quantities `joint` derives with ones from the
+/// pair it fits, printing how far they are from the samples.
+fn fit_jointly(dataset: &mut Dataset, joint: Joint) {
+    let [first, second] = joint.fitted();
+    let (Some(first_fit), Some(second_fit)) =
+        (dataset.fit(first).cloned(), dataset.fit(second).cloned())
+    else {
+        eprintln!(
+            "A joint fit needs {} and {} in the input",
+            first.label(),
+            second.label()
+        );
+        return;
+    };
 
-    for day in &mut data {
-        day.srise = Some(day.srise.as_ref().unwrap().as_num());
-        day.sset = Some(day.sset.as_ref().unwrap().as_num());
-        day.solnoon = Some(day.solnoon.as_ref().unwrap().as_num());
-        day.daylen = match (&day.srise, &day.sset) {
-            (Some(NumOrStr::Num(srise)), Some(NumOrStr::Num(sset))) => {
-                Some(NumOrStr::Num(sset - srise))
-            }
-            _ => None,
-        };
+    for quantity in joint.derived() {
+        let fitment = JointFitment::new(quantity, first_fit.clone(), second_fit.clone());
+        let series = dataset.series(quantity);
+        if series.is_empty() {
+            println!("Joint {}: {}", quantity.label(), fitment);
+        } else {
+            let squares: f64 = series
+                .iter()
+                .map(|&(value, day)| (value - fitment.eval(day as f64)).powi(2))
+                .sum();
+            println!(
+                "Joint {}: {}, RMS {:.1} min",
+                quantity.label(),
+                fitment,
+                (squares / series.len() as f64).sqrt() * 60.0
+            );
+        }
+        dataset.fits.retain(|(q, _)| *q != quantity);
+        dataset.bands.retain(|(q, _)| *q != quantity);
+        dataset.fits.push((quantity, Fitment::Joint(fitment)));
     }
+}
 
-    //let output = serde_json::to_string_pretty(&data).expect("Unable to serialize JSON");
-    //println!("{}", output);
-
-    let root = SVGBackend::new(&args.output, (1024, 768)).into_drawing_area();
-
-    root.fill(&WHITE).unwrap();
-
-    let mut chart = ChartBuilder::on(&root)
-        .caption(
-            args.label.unwrap_or(String::from("Sun Rise/Set/Noon")),
-            ("sans-serif", 50).into_font(),
-        )
-        .margin(5)
-        .x_label_area_size(40)
-        .y_label_area_size(40)
-        .build_cartesian_2d(0..365, 0.0..24.0)
-        .unwrap();
-
-    chart
-        .configure_mesh()
-        .x_labels(28)
-        .y_labels(5)
-        .x_desc("Day")
-        .y_desc("Time/Duration (h)")
-        .draw()
-        .unwrap();
-
-    // series
-    let mut plot_series = |accessor: fn(&Day) -> &NumOrStr, color: RGBColor, label: &str| {
-        chart
-            .draw_series(
-                LineSeries::new(
-                    data.iter()
-                        .map(|day| (day.yday as i32, accessor(day).get_num())),
-                    &color,
-                )
-                .point_size(5),
-            )
-            .unwrap()
-            .label(label)
-            .legend({
-                let color = color;
-                move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], &color)
-            });
+/// Print a robust fit's residual scale and outliers, and flag the outliers
+/// in the dataset. The fit is of the `standard` time series; the outliers
+/// are printed on the clock.
+fn flag_outliers(
+    dataset: &mut Dataset,
+    quantity: Quantity,
+    fit: &RobustFit,
+    standard: &[(f64, u64)],
+) {
+    let series = dataset.series(quantity);
+    let shift = |day: u64| {
+        let index = standard.iter().position(|&(_, d)| d == day);
+        index.map_or(0.0, |index| series[index].0 - standard[index].0)
     };
-
-    plot_series(
-        |day| day.srise.as_ref().unwrap(),
-        RGBColor(255, 0, 0),
-        "Sunrise",
-    );
-
-    plot_series(
-        |day| day.sset.as_ref().unwrap(),
-        RGBColor(0, 255, 0),
-        "Sunset",
+    let moved = standard
+        .iter()
+        .filter(|&&(_, day)| shift(day) != 0.0)
+        .count();
+    let note = if moved > 0 {
+        format!(" ({} on daylight saving time fitted an hour back)", moved)
+    } else {
+        String::new()
+    };
+    println!(
+        "Robust {}: residual scale {:.3}, {} outliers{}",
+        quantity.label(),
+        fit.scale,
+        fit.outliers.len(),
+        note
     );
+    for &(value, day) in &fit.outliers {
+        let (y, m, d) = dataset.date(day);
+        let fitted = fit.fitment.eval(day as f64);
+        println!(
+            "Outlier {}: {:04}-{:02}-{:02} (day {}) {:.2}, fitted {:.2} ({:+.1} robust SDs)",
+            quantity.label(),
+            y,
+            m,
+            d,
+            day,
+            value + shift(day),
+            fitted + shift(day),
+            (value - fitted) / fit.scale
+        );
+        dataset.outliers.push((quantity, day));
+    }
+}
 
-    plot_series(
-        |day| day.solnoon.as_ref().unwrap(),
-        RGBColor(0, 0, 255),
-        "Solar Noon",
+/// Print the position inferred from a dataset's sunrise/sunset times and
+/// whether the claimed position agrees; false if it does not.
+fn infer_position(dataset: &Dataset, utc_offset: Option<f64>, coords: Option<(f64, f64)>) -> bool {
+    let claimed = coords.or_else(|| {
+        dataset
+            .location
+            .as_ref()
+            .map(|location| (location.lat as f64, location.lon as f64))
+    });
+    // without a known offset, assume the claimed place keeps its nominal zone
+    let utc_offset =
+        utc_offset.or_else(|| claimed.map(|(_, longitude)| (longitude / 15.0).round()));
+
+    let Some(fit) = PhysicalFit::fit(&dataset.days, utc_offset) else {
+        eprintln!("Too few days with sunrise and sunset to infer a position");
+        return true;
+    };
+    let (latitude_axis, longitude_axis) = fit.region();
+    let hemisphere =
+        |value: f64, positive, negative| if value >= 0.0 { positive } else { negative };
+
+    let longitude = match (fit.longitude(), fit.utc_offset) {
+        (Some(longitude), Some(utc_offset)) => format!(
+            "{:.2}°{} ± {:.2}° (UTC{:+})",
+            longitude.abs(),
+            hemisphere(longitude, 'E', 'W'),
+            longitude_axis,
+            utc_offset
+        ),
+        _ => format!(
+            "{:.2}° {} of the time zone's meridian ± {:.2}°",
+            (15.0 * fit.noon_offset).abs(),
+            hemisphere(-fit.noon_offset, 'E', 'W'),
+            longitude_axis
+        ),
+    };
+    println!(
+        "Inferred position: {:.2}°{} ± {:.2}°, {} (95%)",
+        fit.latitude.abs(),
+        hemisphere(fit.latitude, 'N', 'S'),
+        latitude_axis,
+        longitude
     );
 
-    plot_series(
-        |day| &day.daylen.as_ref().unwrap(),
-        RGBColor(0, 0, 0),
-        "Day Length",
+    let (Some((latitude, longitude)), Some(utc_offset)) = (claimed, fit.utc_offset) else {
+        return true;
+    };
+    let inside = fit.contains(latitude, longitude, utc_offset);
+    println!(
+        "Claimed {:.2}°{} {:.2}°{}: {} the 95% region (latitude {:+.2}°, longitude {:+.2}°)",
+        latitude.abs(),
+        hemisphere(latitude, 'N', 'S'),
+        longitude.abs(),
+        hemisphere(longitude, 'E', 'W'),
+        if inside { "inside" } else { "outside" },
+        latitude - fit.latitude,
+        longitude - fit.longitude().unwrap_or_default()
     );
-
-    chart
-        .configure_series_labels()
-        .background_style(&WHITE.mix(0.8))
-        .border_style(&BLACK)
-        .position(SeriesLabelPosition::UpperRight)
-        .draw()
-        .unwrap();
-
-    #[derive(Debug)]
-    struct SineFitment {
-        period_mul_2pi: f64,
-        a: f64,
-        b: f64,
-        c: f64,
-        d: f64,
+    if !inside {
+        eprintln!(
+            "{} does not look like data for its claimed location",
+            dataset.label
+        );
     }
+    inside
+}
 
-    impl SineFitment {
-        pub fn new() -> Self {
-            SineFitment {
-                period_mul_2pi: 0.0,
-                a: 0.0,
-                b: 0.0,
-                c: 0.0,
-                d: 0.0,
-            }
-        }
+fn main() {
+    let args = Args::parse();
+
+    if args.transformed.len() > args.input.len() {
+        eprintln!("More --transformed outputs than --input files");
+        std::process::exit(2);
     }
 
-    struct SineFitter {
-        min: f64,
-        min_day: u64,
-        max: f64,
-        max_day: u64,
+    if args.coords.len() > args.input.len() {
+        eprintln!("More --coords than --input files");
+        std::process::exit(2);
     }
 
-    impl SineFitter {
-        fn new() -> Self {
-            SineFitter {
-                min: f64::MAX,
-                min_day: 0,
-                max: f64::MIN,
-                max_day: 0,
+    if let Some(format) = args.validate {
+        let reports: Vec<Report> = args
+            .input
+            .iter()
+            .map(|input| Report::check(input))
+            .collect();
+        match format {
+            ReportFormat::Human => {
+                for report in &reports {
+                    print!("{}", report);
+                }
             }
+            ReportFormat::Json => println!(
+                "{}",
+                serde_json::to_string_pretty(&reports).expect("Unable to serialize JSON")
+            ),
         }
-
-        fn update(&mut self, value: f64, yday: u64) {
-            if value < self.min {
-                self.min = value;
-                self.min_day = yday;
-            }
-            if value > self.max {
-                self.max = value;
-                self.max_day = yday;
+        let failed = reports.iter().any(|report| report.errors > 0);
+        if failed {
+            if args.output.is_some() || args.terminal {
+                eprintln!("Not plotting inputs with errors");
             }
+            std::process::exit(1);
         }
-
-        fn get_midline(&self) -> f64 {
-            (self.min + self.max) / 2.0
+        if args.output.is_none() && !args.terminal && !args.infer {
+            return;
         }
+    }
 
-        fn fit(&self, data: &Vec<(f64, u64)>, period_mul_2pi: f64) -> SineFitment {
-            let mut fitment = SineFitment::new();
+    let mut datasets: Vec<Dataset> = args
+        .input
+        .iter()
+        .enumerate()
+        .map(|(index, input)| {
+            let mut dataset = Dataset::load(input, String::new());
+            dataset.label = match (args.label.get(index), &dataset.location) {
+                (Some(label), _) => label.clone(),
+                (None, Some(location)) => location.short_name(),
+                (None, None) => input
+                    .file_stem()
+                    .map(|stem| stem.to_string_lossy().into_owned())
+                    .unwrap_or_default(),
+            };
+            dataset
+        })
+        .collect();
+
+    for (dataset, &(lat, lon)) in datasets.iter_mut().zip(&args.coords) {
+        dataset.fill_angles(lat, lon);
+    }
 
-            if data.is_empty() {
-                return fitment;
+    if args.infer {
+        let mut consistent = true;
+        for (index, dataset) in datasets.iter().enumerate() {
+            if datasets.len() > 1 {
+                println!("== {} ==", dataset.label);
             }
+            let utc_offset = args.utc_offset.get(index).copied().or_else(|| {
+                dataset
+                    .location
+                    .as_ref()
+                    .and_then(|location| location.utc_offset)
+            });
+            consistent &= infer_position(dataset, utc_offset, args.coords.get(index).copied());
+        }
+        if args.output.is_none() && !args.terminal {
+            std::process::exit(if consistent { 0 } else { 1 });
+        }
+        if !consistent {
+            eprintln!("Some inputs do not match their claimed location");
+        }
+    }
 
-            //{
-            //    a: 1.0,
-            //    b: 2.0 * std::f64::consts::PI / 365.0,
-            //    c: 0.0,
-            //    d: self.get_midline(),
-            //};
-
-            fitment.period_mul_2pi = period_mul_2pi;
-
-            let mag = &mut fitment.a;
-            let period = &mut fitment.b;
-            let shift = &mut fitment.c;
-            let midline = &mut fitment.d;
+    let multiple = datasets.len() > 1;
+    let mut analytics = Vec::new();
 
-            *mag = (self.max - self.min) / 2.0;
-            *midline = self.get_midline();
-            *period = period_mul_2pi * 2.0 * std::f64::consts::PI / 365.0;
+    for (index, dataset) in datasets.iter_mut().enumerate() {
+        if multiple {
+            println!("== {} ==", dataset.label);
+        }
 
-            eprintln!(
-                "Magnitude = A = |{:.2} - {:.2}| / 2 = {:.2}",
-                self.max, self.min, *mag
-            );
-            eprintln!(
-                "Midline = D = ({:.2} + {:.2}) / 2 = {:.2}",
-                self.max, self.min, *midline
-            );
-            eprintln!(
-                "Period = 365 = 2\u{03C0}/B ==> B = {:.0}\u{03C0} / 365",
-                2.0 * period_mul_2pi
+        let fitters = dataset.fitters();
+        // the fit report goes with the transformed output, as it always has
+        let report = args.transformed.get(index).is_some();
+
+        for (quantity, mm) in fitters.iter().filter(|_| report) {
+            println!(
+                "Min/Max {}: {} (day {}) - {} (day {})",
+                quantity.label(),
+                mm.min,
+                mm.min_day,
+                mm.max,
+                mm.max_day
             );
+        }
 
-            // find ydar closest to midline
-            let mut closest = data[0];
-            let mut closest_last_pt = data[0];
-            let mut closest_deriv = 0.0;
-            for d in data {
-                let deriv = (d.0 - closest_last_pt.0) / (d.1 - closest_last_pt.1) as f64;
-                closest_last_pt = *d;
-                if (d.0 - fitment.d).abs() < (closest.0 - fitment.d).abs() {
-                    closest = *d;
-                    closest_deriv = deriv;
+        // auto may pick the physical model
+        let physical = if fitters.iter().any(|(quantity, _)| {
+            let models = ModelChoice::models_for(&args.model, *quantity);
+            models.contains(&Model::Physical) || models.contains(&Model::Auto)
+        }) {
+            let utc_offset = args.utc_offset.get(index).copied().or_else(|| {
+                dataset
+                    .location
+                    .as_ref()
+                    .and_then(|location| location.utc_offset)
+            });
+            fit_physical(dataset, utc_offset)
+        } else {
+            None
+        };
+
+        for (quantity, mm) in &fitters {
+            let series = dataset.series(*quantity);
+            // the hour of daylight saving time is not an outlier
+            let robust = args.robust.and_then(|loss| {
+                let standard = dataset.standard_time(*quantity);
+                let fit = RobustFit::fit(&standard, quantity.period_mul_2pi(), loss)?;
+                flag_outliers(dataset, *quantity, &fit, &standard);
+                Some((fit, standard))
+            });
+            // about the sine shown, on the samples it was fitted to
+            let intervals = |fitment: &SineFitment| match &robust {
+                Some((_, standard)) => SineIntervals::estimate(fitment, standard),
+                None => SineIntervals::estimate(fitment, &series),
+            };
+            let sine = || {
+                let fitment = match &robust {
+                    Some((fit, _)) => fit.fitment.clone(),
+                    None => {
+                        if report {
+                            eprint!("{}", mm.working(&series, quantity.period_mul_2pi()));
+                        }
+                        mm.fit(&series, quantity.period_mul_2pi())
+                    }
+                };
+                if report {
+                    println!("Fitment {}: {:?}", quantity.label(), fitment);
+                    if let Some(intervals) = intervals(&fitment) {
+                        println!("Intervals {}: {}", quantity.label(), intervals);
+                    }
+                    println!("------------------------------");
                 }
+                Fitment::Sine(fitment)
+            };
+            let points: Vec<(f64, f64)> = series
+                .iter()
+                .map(|&(value, day)| (day as f64, value))
+                .collect();
+            let models = ModelChoice::models_for(&args.model, *quantity);
+            let validation = if args.cross_validate || models.contains(&Model::Auto) {
+                CrossValidation::run(dataset, *quantity, args.folds.map(|folds| folds as usize))
+            } else {
+                None
+            };
+            if let Some(validation) = &validation {
+                println!("{}", validation);
             }
-
-            *shift = closest.1 as f64;
-            if (*shift > (182.5)) && (*shift < (365.0)) {
-                //*shift = *shift - 365.0;
-                //*shift = 365.0 - *shift;
-                if closest_deriv > 0.00 {
-                    *shift = *shift - 365.0 / (2.0 * period_mul_2pi);
-                } else {
-                    *shift = 365.0 / (2.0 * period_mul_2pi) - *shift;
+            for model in models {
+                let (model, harmonics) = match model {
+                    Model::Auto => {
+                        let best = validation
+                            .as_ref()
+                            .and_then(CrossValidation::best)
+                            .unwrap_or(Candidate::Sine);
+                        println!("Auto {}: {}", quantity.label(), best);
+                        match best {
+                            Candidate::Fourier(harmonics) => (Model::Fourier, harmonics),
+                            candidate => (candidate.model(), args.harmonics as usize),
+                        }
+                    }
+                    model => (model, args.harmonics as usize),
+                };
+                let fitment = match model {
+                    Model::Sine => sine(),
+                    // without a physical fit the sine stands in
+                    Model::Physical => match &physical {
+                        Some(fit) => Fitment::Physical(*quantity, fit.clone()),
+                        None => sine(),
+                    },
+                    Model::Spline => {
+                        let Some(fitment) = SplineFitment::fit(&points, YEAR) else {
+                            eprintln!("Too few days of {} for a spline", quantity.label());
+                            continue;
+                        };
+                        if report {
+                            println!("Fitment {}: {}", quantity.label(), fitment);
+                            println!("------------------------------");
+                        }
+                        Fitment::Spline(fitment)
+                    }
+                    Model::Fourier => {
+                        let Some(fitment) = FourierFitment::fit(&points, YEAR, harmonics) else {
+                            eprintln!(
+                                "Too few days of {} for {} harmonics",
+                                quantity.label(),
+                                harmonics
+                            );
+                            continue;
+                        };
+                        if report {
+                            println!("Fitment {}: {}", quantity.label(), fitment);
+                            println!("------------------------------");
+                        }
+                        Fitment::Fourier(fitment)
+                    }
+                    Model::Auto => unreachable!("auto resolves to a model above"),
+                };
+                // the band goes around the fit, not those for comparison
+                if args.prediction_band && dataset.fit(*quantity).is_none() {
+                    if let Fitment::Sine(sine) = &fitment {
+                        if let Some(intervals) = intervals(sine) {
+                            dataset.bands.push((*quantity, intervals));
+                        }
+                    }
                 }
+                dataset.fits.push((*quantity, fitment));
             }
+        }
 
-            eprintln!(
-                "Phase = C/B = {} ==> C = {}*2\u{03C0}/365",
-                closest.1, *shift
-            );
-
-            //eprintln!(
-            //    "Closest: {:?}, Shift: {}; deriv: {}",
-            //    closest, *shift, closest_deriv
-            //);
-
-            *shift = *shift * if closest_deriv >= 0.01 { -1.0 } else { 1.0 };
+        if let Some(joint) = args.joint {
+            fit_jointly(dataset, joint);
+        }
 
-            fitment
+        if args.chart == Chart::Eot {
+            match EquationOfTime::derive(dataset) {
+                Some(equation) => {
+                    println!("Equation of time {}: {}", dataset.label, equation);
+                    let utc_offset = args.utc_offset.get(index).copied().or_else(|| {
+                        dataset
+                            .location
+                            .as_ref()
+                            .and_then(|location| location.utc_offset)
+                    });
+                    if let Some(utc_offset) = utc_offset {
+                        println!(
+                            "Longitude {}: {:.2}° from mean solar noon at UTC{:+}",
+                            dataset.label,
+                            equation.longitude(utc_offset),
+                            utc_offset
+                        );
+                    }
+                }
+                None => eprintln!(
+                    "Too few solar noons in {} for the equation of time",
+                    dataset.label
+                ),
+            }
         }
-    }
 
-    if let Some(transformed) = args.transformed {
-        let mut xdata = Vec::new();
-
-        let mut mmsrise = SineFitter::new();
-        let mut mmsset = SineFitter::new();
-        let mut mmsolnoon = SineFitter::new();
-        let mut mmdaylen = SineFitter::new();
-
-        for dtp in &data {
-            xdata.push(XDay {
-                yday: dtp.yday,
-                srise: dtp.srise.clone(),
-                sset: dtp.sset.clone(),
-                solnoon: dtp.solnoon.clone(),
-                daylen: dtp.daylen.clone(),
-            });
-            mmsrise.update(dtp.srise.as_ref().unwrap().get_num(), dtp.yday);
-            mmsset.update(dtp.sset.as_ref().unwrap().get_num(), dtp.yday);
-            mmsolnoon.update(dtp.solnoon.as_ref().unwrap().get_num(), dtp.yday);
-            mmdaylen.update(dtp.daylen.as_ref().unwrap().get_num(), dtp.yday);
+        if let Some(format) = args.analytics {
+            let report = Analytics::compute(dataset);
+            if report.years.is_empty() {
+                eprintln!("No days in {} for analytics", dataset.label);
+            } else if format == ReportFormat::Human {
+                print!("{}", report);
+            } else {
+                analytics.push(report);
+            }
         }
 
-        let output = serde_json::to_string_pretty(&xdata).expect("Unable to serialize JSON");
+        if let Some(transformed) = args.transformed.get(index) {
+            let output = serde_json::to_string_pretty(&dataset.transformed(
+                args.resample,
+                args.exclude_outliers,
+                args.change_rates,
+            ))
+            .expect("Unable to serialize JSON");
 
-        fs::write(&transformed, output).expect("Unable to write file");
+            fs::write(transformed, output).expect("Unable to write file");
+        }
+    }
 
+    if args.analytics == Some(ReportFormat::Json) {
         println!(
-            "Min/Max Sunrise: {} (day {}) - {} (day {})",
-            mmsrise.min, mmsrise.min_day, mmsrise.max, mmsrise.max_day
-        );
-        println!(
-            "Min/Max Sunset: {} (day {}) - {} (day {})",
-            mmsset.min, mmsset.min_day, mmsset.max, mmsset.max_day
-        );
-        println!(
-            "Min/Max Solar Noon: {} (day {}) - {} (day {})",
-            mmsolnoon.min, mmsolnoon.min_day, mmsolnoon.max, mmsolnoon.max_day
-        );
-        println!(
-            "Min/Max Day Length: {} (day {}) - {} (day {})",
-            mmdaylen.min, mmdaylen.min_day, mmdaylen.max, mmdaylen.max_day
+            "{}",
+            serde_json::to_string_pretty(&analytics).expect("Unable to serialize JSON")
         );
+    }
 
-        let fit_srise = mmsrise.fit(
-            &data
-                .iter()
-                .map(|d| (d.srise.as_ref().unwrap().get_num(), d.yday))
-                .collect(),
-            1.0,
-        );
-        println!("Fitment Sunrise: {:?}", fit_srise);
-        println!("------------------------------");
+    let caption = if multiple {
+        datasets
+            .iter()
+            .map(|dataset| dataset.label.as_str())
+            .collect::<Vec<_>>()
+            .join(" vs ")
+    } else {
+        args.label
+            .first()
+            .cloned()
+            .or_else(|| datasets[0].location.as_ref().map(|l| l.full_name()))
+            .unwrap_or(String::from("Sun Rise/Set/Noon"))
+    };
 
-        let fit_sset = mmsset.fit(
-            &data
-                .iter()
-                .map(|d| (d.sset.as_ref().unwrap().get_num(), d.yday))
-                .collect(),
-            1.0,
-        );
-        println!("Fitment Sunset: {:?}", fit_sset);
-        println!("------------------------------");
+    if args.terminal {
+        let (width, rows) = term::terminal_size();
+        let height = (rows.saturating_sub(2)).min(width / 3);
+        let color = std::io::stdout().is_terminal() && std::env::var_os("NO_COLOR").is_none();
+        for dataset in &datasets {
+            print!(
+                "{}",
+                term::render_chart(&dataset.label, dataset, width, height, color)
+            );
+        }
+    }
 
-        let fit_solnoon = mmsolnoon.fit(
-            &data
-                .iter()
-                .map(|d| (d.solnoon.as_ref().unwrap().get_num(), d.yday))
-                .collect(),
-            2.0,
-        );
-        println!("Fitment Solar Noon: {:?}", fit_solnoon);
-        println!("------------------------------");
+    let Some(output) = args.output else {
+        return;
+    };
 
-        let fit_daylen = mmdaylen.fit(
-            &data
-                .iter()
-                .map(|d| (d.daylen.as_ref().unwrap().get_num(), d.yday))
-                .collect(),
-            1.0,
-        );
-        println!("Fitment Day Length: {:?}", fit_daylen);
-        println!("------------------------------");
-
-        chart
-            .draw_series(LineSeries::new(
-                (0..365).map(|x| {
-                    (
-                        x,
-                        fit_srise.a
-                            * (fit_srise.b * x as f64
-                                + fit_srise.c * 2.0 * std::f64::consts::PI / 365.0)
-                                .sin()
-                            + fit_srise.d,
-                    )
-                }),
-                &RGBColor(255, 0, 0),
-            ))
-            .unwrap()
-            .label("Sunrise Fitment");
-
-        chart
-            .draw_series(LineSeries::new(
-                (0..365).map(|x| {
-                    (
-                        x,
-                        fit_sset.a
-                            * (fit_sset.b * x as f64
-                                + fit_sset.c * 2.0 * std::f64::consts::PI / 365.0)
-                                .sin()
-                            + fit_sset.d,
-                    )
-                }),
-                &RGBColor(0, 255, 0),
-            ))
-            .unwrap()
-            .label("Sunset Fitment");
-
-        chart
-            .draw_series(LineSeries::new(
-                (0..365).map(|x| {
-                    (
-                        x,
-                        fit_solnoon.a
-                            * (fit_solnoon.b * x as f64
-                                + fit_solnoon.c * 4.0 * std::f64::consts::PI / 365.0)
-                                .sin()
-                            + fit_solnoon.d,
-                    )
-                }),
-                &RGBColor(0, 0, 255),
-            ))
-            .unwrap()
-            .label("Solar Noon Fitment");
-
-        chart
-            .draw_series(LineSeries::new(
-                (0..365).map(|x| {
-                    (
-                        x,
-                        fit_daylen.a
-                            * (fit_daylen.b * x as f64
-                                + fit_daylen.c * 2.0 * std::f64::consts::PI / 365.0)
-                                .sin()
-                            + fit_daylen.d,
-                    )
-                }),
-                &RGBColor(0, 0, 0),
-            ))
-            .unwrap()
-            .label("Day Length Fitment");
+    let format =
+        args.format
+            .unwrap_or_else(|| match output.extension().and_then(|ext| ext.to_str()) {
+                Some("html" | "htm") => Format::Html,
+                _ => Format::Svg,
+            });
+
+    if format == Format::Html {
+        if args.chart != Chart::Line {
+            eprintln!("HTML output only supports the line chart");
+            std::process::exit(2);
+        }
+        html::write_html_chart(&output, &caption, &datasets);
+        return;
     }
 
-    root.present().unwrap();
+    match args.chart {
+        Chart::Line => {
+            plot::draw_line_chart(&output, &caption, &datasets, args.layout, Unit::Hours)
+        }
+        Chart::Band => plot::draw_band_chart(&output, &caption, &datasets),
+        Chart::Rates => plot::draw_rate_chart(&output, &caption, &datasets),
+        Chart::Eot => plot::draw_eot_chart(&output, &caption, &datasets),
+        Chart::Angles => {
+            plot::draw_line_chart(&output, &caption, &datasets, args.layout, Unit::Degrees)
+        }
+    }
 }
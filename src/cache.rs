@@ -0,0 +1,68 @@
+//! On-disk cache of raw API responses, addressed by a hash of the request
+//! (endpoint plus query, without the per-call signature).
+
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, SystemTime};
+
+use sha1::{Digest, Sha1};
+
+pub struct Cache {
+    dir: PathBuf,
+    ttl: Duration,
+}
+
+impl Cache {
+    pub fn new(dir: impl Into<PathBuf>, ttl: Duration) -> Self {
+        Cache {
+            dir: dir.into(),
+            ttl,
+        }
+    }
+
+    /// `$XDG_CACHE_HOME/sun`, falling back to `~/.cache/sun`.
+    pub fn default_dir() -> Option<PathBuf> {
+        std::env::var_os("XDG_CACHE_HOME")
+            .filter(|dir| !dir.is_empty())
+            .map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| Path::new(&home).join(".cache")))
+            .map(|dir| dir.join("sun"))
+    }
+
+    pub fn dir(&self) -> &Path {
+        &self.dir
+    }
+
+    fn path(&self, key: &str) -> PathBuf {
+        let digest = Sha1::digest(key.as_bytes());
+        let name: String = digest.iter().map(|byte| format!("{:02x}", byte)).collect();
+        self.dir.join(format!("{}.json", name))
+    }
+
+    /// The cached body for `key` if there is one younger than the TTL, or of
+    /// any age with `stale_ok`.
+    pub fn get(&self, key: &str, stale_ok: bool) -> Option<String> {
+        let path = self.path(key);
+        let modified = fs::metadata(&path).and_then(|meta| meta.modified()).ok()?;
+        let age = SystemTime::now()
+            .duration_since(modified)
+            .unwrap_or_default();
+        if !stale_ok && age > self.ttl {
+            return None;
+        }
+        fs::read_to_string(path).ok()
+    }
+
+    /// Store a body; failing to cache is reported but not fatal.
+    pub fn put(&self, key: &str, body: &str) {
+        let path = self.path(key);
+        // write then rename so readers never see a partial file
+        let tmp = path.with_extension("tmp");
+        let result = fs::create_dir_all(&self.dir)
+            .and_then(|_| fs::write(&tmp, body))
+            .and_then(|_| fs::rename(&tmp, &path));
+        if let Err(e) = result {
+            eprintln!("Unable to write cache {}: {}", path.display(), e);
+        }
+    }
+}
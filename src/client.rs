@@ -0,0 +1,438 @@
+//! Signed client for the timeanddate.com API. It reuses libtad's request and
+//! response models, but does the HTTP itself so requests can be retried
+//! with backoff, throttled to a request rate shared by all concurrent
+//! fetches, rate limits honoured, failures classified and the base URL
+//! pointed elsewhere.
+
+use std::fmt;
+use std::time::Duration;
+
+use base64::Engine;
+use hmac::{Hmac, Mac};
+use libtad_rs::service::astronomy::{AstroEventRequest, AstronomyResponse};
+use libtad_rs::service::places::{PlacesRequest, PlacesResponse};
+use libtad_rs::service::time::{TimeserviceRequest, TimeserviceResponse};
+use libtad_rs::ApiError;
+use serde::de::DeserializeOwned;
+use serde::Serialize;
+use sha1::Sha1;
+use tokio::sync::Mutex;
+use tokio::time::Instant;
+
+use crate::cache::Cache;
+
+pub const BASE_URL: &str = "https://api.xmltime.com";
+const VERSION: &str = "3";
+
+/// Requests per second sent unless told otherwise.
+pub const DEFAULT_RATE: f64 = 4.0;
+
+/// Error messages with which the API turns requests away for want of
+/// credits or for going over the request rate. Its error bodies carry no
+/// codes, so these are matched as whole phrases, not any word in them.
+const QUOTA_ERRORS: [&str; 6] = [
+    "no credits",
+    "out of credits",
+    "insufficient credits",
+    "credit limit exceeded",
+    "rate limit exceeded",
+    "too many requests",
+];
+
+/// How often and how patiently to retry transient failures.
+#[derive(Debug, Clone)]
+pub struct RetryPolicy {
+    /// Attempts after the first one
+    pub retries: u32,
+    /// Delay before the first retry; doubled for every further one
+    pub base_delay: Duration,
+    pub max_delay: Duration,
+}
+
+impl Default for RetryPolicy {
+    fn default() -> Self {
+        RetryPolicy {
+            retries: 3,
+            base_delay: Duration::from_secs(1),
+            max_delay: Duration::from_secs(30),
+        }
+    }
+}
+
+impl RetryPolicy {
+    fn backoff(&self, attempt: u32) -> Duration {
+        self.base_delay
+            .saturating_mul(2u32.saturating_pow(attempt))
+            .min(self.max_delay)
+    }
+}
+
+/// Why a request failed, with a distinct process exit code for each class.
+#[derive(Debug)]
+pub enum FetchError {
+    /// Bad access key, secret or signature
+    Auth(String),
+    /// Out of credits or rate limited past the retries
+    Quota(String),
+    /// The location could not be resolved
+    PlaceNotFound(String),
+    /// Connection failures, timeouts and server errors
+    Network(String),
+    /// Any other error reported by the API
+    Api(String),
+    /// A response that could not be parsed
+    Decode(String),
+    /// Offline and nothing cached for the request
+    Offline(String),
+}
+
+impl FetchError {
+    pub fn exit_code(&self) -> i32 {
+        match self {
+            FetchError::Auth(_) => 3,
+            FetchError::Quota(_) => 4,
+            FetchError::PlaceNotFound(_) => 5,
+            FetchError::Network(_) => 6,
+            FetchError::Api(_) => 7,
+            FetchError::Decode(_) => 8,
+            FetchError::Offline(_) => 9,
+        }
+    }
+
+    /// Classify the messages of an API error body.
+    fn from_api(error: ApiError) -> Self {
+        let message = error.errors.join(", ");
+        let lower = message.to_lowercase();
+        let mentions = |words: &[&str]| words.iter().any(|word| lower.contains(word));
+
+        if mentions(&[
+            "accesskey",
+            "access key",
+            "signature",
+            "authenticat",
+            "denied",
+        ]) {
+            FetchError::Auth(message)
+        } else if QUOTA_ERRORS.iter().any(|quota| {
+            error
+                .errors
+                .iter()
+                .any(|e| e.trim().to_lowercase().starts_with(quota))
+        }) {
+            FetchError::Quota(message)
+        } else if mentions(&["place", "location", "no matches", "not found"]) {
+            FetchError::PlaceNotFound(message)
+        } else {
+            FetchError::Api(message)
+        }
+    }
+}
+
+impl fmt::Display for FetchError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            FetchError::Auth(e) => write!(f, "Authentication failed: {}", e),
+            FetchError::Quota(e) => write!(f, "Quota exceeded: {}", e),
+            FetchError::PlaceNotFound(e) => write!(f, "Place not found: {}", e),
+            FetchError::Network(e) => write!(f, "Network error: {}", e),
+            FetchError::Api(e) => write!(f, "API error: {}", e),
+            FetchError::Decode(e) => write!(f, "Unexpected response: {}", e),
+            FetchError::Offline(e) => write!(f, "Offline: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for FetchError {}
+
+/// The outcome of one attempt: the response body, a failure, or worth
+/// another try (after at least the given delay, when the server asked for
+/// one).
+enum Attempt {
+    Done(Result<String, FetchError>),
+    Retry(FetchError, Option<Duration>),
+}
+
+/// A transport error with its causes, minus the URL (it carries the access
+/// key and signature).
+fn describe(e: reqwest::Error) -> String {
+    use std::error::Error;
+
+    let e = e.without_url();
+    let mut message = e.to_string();
+    let mut source = e.source();
+    while let Some(cause) = source {
+        message = format!("{}: {}", message, cause);
+        source = cause.source();
+    }
+    message
+}
+
+/// Spaces requests at least `interval` apart across everyone sharing the
+/// client, and holds them all back while the server asked to wait.
+struct Throttle {
+    interval: Duration,
+    /// Earliest instant the next request may go out
+    next: Mutex<Instant>,
+}
+
+impl Throttle {
+    fn new(rate: f64) -> Self {
+        Throttle {
+            interval: if rate > 0.0 {
+                Duration::from_secs_f64(1.0 / rate)
+            } else {
+                Duration::ZERO
+            },
+            next: Mutex::new(Instant::now()),
+        }
+    }
+
+    /// Wait for this request's slot.
+    async fn wait(&self) {
+        let slot = {
+            let mut next = self.next.lock().await;
+            let slot = (*next).max(Instant::now());
+            *next = slot + self.interval;
+            slot
+        };
+        tokio::time::sleep_until(slot).await;
+    }
+
+    /// Send nothing for `delay`.
+    async fn pause(&self, delay: Duration) {
+        let mut next = self.next.lock().await;
+        *next = (*next).max(Instant::now() + delay);
+    }
+}
+
+/// Authentication parameters appended to every query.
+#[derive(Serialize)]
+struct Auth<'a> {
+    accesskey: &'a str,
+    signature: &'a str,
+    timestamp: &'a str,
+    version: &'a str,
+}
+
+pub struct Client {
+    http: reqwest::Client,
+    base_url: String,
+    access_key: String,
+    secret_key: String,
+    retry: RetryPolicy,
+    throttle: Throttle,
+    cache: Option<Cache>,
+    offline: bool,
+}
+
+impl Client {
+    pub fn new(access_key: String, secret_key: String) -> Self {
+        Client {
+            http: reqwest::Client::new(),
+            base_url: BASE_URL.to_string(),
+            access_key,
+            secret_key,
+            retry: RetryPolicy::default(),
+            throttle: Throttle::new(DEFAULT_RATE),
+            cache: None,
+            offline: false,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    pub fn with_retry(mut self, retry: RetryPolicy) -> Self {
+        self.retry = retry;
+        self
+    }
+
+    /// Send at most `rate` requests per second, however many fetches share
+    /// the client; 0 for no limit.
+    pub fn with_rate(mut self, rate: f64) -> Self {
+        self.throttle = Throttle::new(rate);
+        self
+    }
+
+    pub fn with_cache(mut self, cache: Cache) -> Self {
+        self.cache = Some(cache);
+        self
+    }
+
+    /// Answer from the cache only, whatever the age of the entries.
+    pub fn with_offline(mut self, offline: bool) -> Self {
+        self.offline = offline;
+        self
+    }
+
+    pub async fn get_astro_events(
+        &self,
+        request: &AstroEventRequest,
+    ) -> Result<AstronomyResponse, FetchError> {
+        self.call("astronomy", request).await
+    }
+
+    pub async fn get_places(&self, request: &PlacesRequest) -> Result<PlacesResponse, FetchError> {
+        self.call("places", request).await
+    }
+
+    pub async fn get_current_time(
+        &self,
+        request: &TimeserviceRequest,
+    ) -> Result<TimeserviceResponse, FetchError> {
+        self.call("timeservice", request).await
+    }
+
+    async fn call<Req: Serialize, Resp: DeserializeOwned>(
+        &self,
+        path: &str,
+        request: &Req,
+    ) -> Result<Resp, FetchError> {
+        let query = serde_url_params::to_string(request)
+            .map_err(|e| FetchError::Decode(format!("request: {}", e)))?;
+        let key = format!("{}/{}?{}", self.base_url.trim_end_matches('/'), path, query);
+
+        if let Some(body) = self
+            .cache
+            .as_ref()
+            .and_then(|cache| cache.get(&key, self.offline))
+        {
+            match serde_json::from_str(&body) {
+                Ok(response) => return Ok(response),
+                Err(e) => eprintln!("Ignoring unreadable cache entry: {}", e),
+            }
+        }
+        if self.offline {
+            return Err(FetchError::Offline(format!(
+                "no cached response for {}",
+                key
+            )));
+        }
+        if self.access_key.is_empty() || self.secret_key.is_empty() {
+            return Err(FetchError::Auth(
+                "no API key or secret key given".to_string(),
+            ));
+        }
+
+        let body = self.fetch(path, &query).await?;
+        let response =
+            serde_json::from_str(&body).map_err(|e| FetchError::Decode(e.to_string()))?;
+        if let Some(cache) = &self.cache {
+            cache.put(&key, &body);
+        }
+        Ok(response)
+    }
+
+    /// GET with retries; the body of the successful response.
+    async fn fetch(&self, path: &str, query: &str) -> Result<String, FetchError> {
+        let mut attempt = 0;
+        loop {
+            match self.attempt(path, query).await {
+                Attempt::Done(result) => return result,
+                Attempt::Retry(e, _) if attempt >= self.retry.retries => return Err(e),
+                Attempt::Retry(e, retry_after) => {
+                    let delay = match retry_after {
+                        // the server's wait holds back every fetch
+                        Some(delay) => {
+                            self.throttle.pause(delay).await;
+                            delay
+                        }
+                        None => self.retry.backoff(attempt),
+                    };
+                    eprintln!(
+                        "{}; retrying in {:.1}s ({}/{})",
+                        e,
+                        delay.as_secs_f64(),
+                        attempt + 1,
+                        self.retry.retries
+                    );
+                    tokio::time::sleep(delay).await;
+                    attempt += 1;
+                }
+            }
+        }
+    }
+
+    async fn attempt(&self, path: &str, query: &str) -> Attempt {
+        self.throttle.wait().await;
+        let url = self.signed_url(path, query);
+
+        let response = match self.http.get(&url).send().await {
+            Ok(response) => response,
+            Err(e) => return Attempt::Retry(FetchError::Network(describe(e)), None),
+        };
+
+        let status = response.status();
+        let retry_after = response
+            .headers()
+            .get(reqwest::header::RETRY_AFTER)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.trim().parse().ok())
+            .map(Duration::from_secs);
+
+        let body = match response.text().await {
+            Ok(body) => body,
+            Err(e) => return Attempt::Retry(FetchError::Network(describe(e)), None),
+        };
+        let api_error = serde_json::from_str::<ApiError>(&body).ok();
+        let reason = || {
+            api_error
+                .as_ref()
+                .map(|error| error.errors.join(", "))
+                .unwrap_or_else(|| status.to_string())
+        };
+
+        if status == reqwest::StatusCode::TOO_MANY_REQUESTS {
+            return Attempt::Retry(FetchError::Quota(reason()), retry_after);
+        }
+        if status == reqwest::StatusCode::PAYMENT_REQUIRED {
+            return Attempt::Done(Err(FetchError::Quota(reason())));
+        }
+        if status.is_server_error() {
+            return Attempt::Retry(FetchError::Network(reason()), retry_after);
+        }
+        if status == reqwest::StatusCode::UNAUTHORIZED || status == reqwest::StatusCode::FORBIDDEN {
+            return Attempt::Done(Err(FetchError::Auth(reason())));
+        }
+        if let Some(error) = api_error {
+            return Attempt::Done(Err(FetchError::from_api(error)));
+        }
+        if !status.is_success() {
+            return Attempt::Done(Err(FetchError::Api(status.to_string())));
+        }
+
+        Attempt::Done(Ok(body))
+    }
+
+    /// The request URL with the HMAC-SHA1 signature over access key, path
+    /// and timestamp that the API expects.
+    fn signed_url(&self, path: &str, query: &str) -> String {
+        let timestamp = chrono::Utc::now().format("%FT%T").to_string();
+
+        let mut mac = Hmac::<Sha1>::new_from_slice(self.secret_key.as_bytes())
+            .expect("HMAC takes keys of any length");
+        mac.update(format!("{}{}{}", self.access_key, path, timestamp).as_bytes());
+        let signature =
+            base64::engine::general_purpose::STANDARD.encode(mac.finalize().into_bytes());
+
+        let auth = serde_url_params::to_string(&Auth {
+            accesskey: &self.access_key,
+            signature: &signature,
+            timestamp: &timestamp,
+            version: VERSION,
+        })
+        .expect("Unable to encode query");
+
+        let separator = if query.is_empty() { "" } else { "&" };
+        format!(
+            "{}/{}?{}{}{}",
+            self.base_url.trim_end_matches('/'),
+            path,
+            query,
+            separator,
+            auth
+        )
+    }
+}
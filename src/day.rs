@@ -0,0 +1,958 @@
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+use serde_this_or_that::{as_f64, as_u64};
+
+use crate::fit::{
+    apparent_noon, daylight_saving, Fitment, PeriodicSpline, SineFitter, SineIntervals, YEAR,
+};
+use crate::place::AstroInfo;
+use crate::solar;
+
+#[derive(Serialize, Deserialize, Debug, Clone)]
+#[serde(untagged)]
+pub enum NumOrStr {
+    Str(String),
+    #[serde(deserialize_with = "as_f64")]
+    Num(f64),
+}
+
+impl NumOrStr {
+    pub fn as_num(&self) -> Self {
+        match self {
+            NumOrStr::Str(ref s) => {
+                let parts: Vec<&str> = s.split(|c| ":,".contains(c)).collect();
+                let h = parts[0].trim_end_matches('°').parse::<f64>().unwrap();
+                let m = parts.get(1).map_or(0.0, |m| m.parse::<f64>().unwrap());
+                NumOrStr::Num(h + m / 60.0)
+            }
+            _ => self.clone(),
+        }
+    }
+
+    pub fn as_str(&self) -> Self {
+        match self {
+            NumOrStr::Num(n) => {
+                NumOrStr::Str(format!("{}:{}", (*n as u64), ((n.fract() * 60.0) as u64)))
+            }
+            _ => self.clone(),
+        }
+    }
+
+    /// The value as a number, or `None` for text `as_num` cannot read.
+    pub fn checked_num(&self) -> Option<f64> {
+        match self {
+            NumOrStr::Num(n) => Some(*n),
+            NumOrStr::Str(s) => {
+                let mut parts = s.split(|c| ":,".contains(c));
+                let h = parts.next()?.trim_end_matches('°').parse::<f64>().ok()?;
+                let m = match parts.next() {
+                    Some(m) => m.parse::<f64>().ok()?,
+                    None => 0.0,
+                };
+                Some(h + m / 60.0)
+            }
+        }
+    }
+
+    pub fn get_num(&self) -> f64 {
+        match self {
+            NumOrStr::Num(n) => *n,
+            _ => self.as_num().get_num(),
+        }
+    }
+}
+
+impl std::fmt::Display for NumOrStr {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            NumOrStr::Str(s) => write!(f, "{}", s),
+            NumOrStr::Num(n) => write!(f, "{}:{}", (*n as u64), ((n.fract() * 60.0) as u64)),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug, Default, Clone)]
+pub struct Day {
+    #[serde(deserialize_with = "as_u64")]
+    pub y: u64,
+    #[serde(deserialize_with = "as_u64")]
+    pub m: u64,
+    #[serde(deserialize_with = "as_u64")]
+    pub d: u64,
+    /// Day of the year; checked against (and computed from) the date
+    #[serde(default, deserialize_with = "as_u64")]
+    pub yday: u64,
+    pub srise: Option<NumOrStr>,
+    pub sset: Option<NumOrStr>,
+    pub solnoon: Option<NumOrStr>,
+    pub daylen: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub civdawn: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub civdusk: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nautdawn: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub nautdusk: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub astrodawn: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub astrodusk: Option<NumOrStr>,
+    /// Azimuth of the sun at sunrise, degrees from north
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub sriseaz: Option<NumOrStr>,
+    /// Azimuth of the sun at sunset, degrees from north
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub ssetaz: Option<NumOrStr>,
+    /// Altitude of the sun at meridian transit, degrees
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub noonalt: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mrise: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mset: Option<NumOrStr>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mtransit: Option<NumOrStr>,
+    /// Moon phase name as reported by the API, e.g. "waxingcrescent"
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub mphase: Option<String>,
+    /// Illuminated fraction of the moon's disc, percent
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub millum: Option<NumOrStr>,
+}
+
+/// Whether a Gregorian year has a 29th of February.
+pub fn is_leap_year(y: u64) -> bool {
+    (y.is_multiple_of(4) && !y.is_multiple_of(100)) || y.is_multiple_of(400)
+}
+
+/// 365 or 366.
+pub fn days_in_year(y: u64) -> u64 {
+    365 + u64::from(is_leap_year(y))
+}
+
+/// Days in month `m` of year `y`; 0 for a month that does not exist.
+pub fn days_in_month(y: u64, m: u64) -> u64 {
+    match m {
+        1 | 3 | 5 | 7 | 8 | 10 | 12 => 31,
+        4 | 6 | 9 | 11 => 30,
+        2 => 28 + u64::from(is_leap_year(y)),
+        _ => 0,
+    }
+}
+
+/// Day of the year (1-based) for a Gregorian calendar date.
+pub fn day_of_year(y: u64, m: u64, d: u64) -> u64 {
+    const CUMULATIVE: [u64; 12] = [0, 31, 59, 90, 120, 151, 181, 212, 243, 273, 304, 334];
+    CUMULATIVE[(m.clamp(1, 12) - 1) as usize] + d + u64::from(is_leap_year(y) && m > 2)
+}
+
+impl Day {
+    /// Convert "HH:MM" times (and "D°"/"D:MM" angles) to numbers and derive
+    /// the day length from sunrise and sunset.
+    pub fn normalize(&mut self) {
+        self.srise = self.srise.as_ref().map(NumOrStr::as_num);
+        self.sset = self.sset.as_ref().map(NumOrStr::as_num);
+        self.solnoon = self.solnoon.as_ref().map(NumOrStr::as_num);
+        for extra in [
+            &mut self.civdawn,
+            &mut self.civdusk,
+            &mut self.nautdawn,
+            &mut self.nautdusk,
+            &mut self.astrodawn,
+            &mut self.astrodusk,
+            &mut self.sriseaz,
+            &mut self.ssetaz,
+            &mut self.noonalt,
+            &mut self.mrise,
+            &mut self.mset,
+            &mut self.mtransit,
+            &mut self.millum,
+        ] {
+            *extra = extra.as_ref().map(NumOrStr::as_num);
+        }
+        self.daylen = match (&self.srise, &self.sset) {
+            (Some(NumOrStr::Num(srise)), Some(NumOrStr::Num(sset))) => {
+                Some(NumOrStr::Num(sset - srise))
+            }
+            _ => None,
+        };
+    }
+}
+
+#[derive(Serialize, Debug)]
+pub struct XDay {
+    #[serde(rename(serialize = "Day Number"))]
+    pub yday: u64,
+    #[serde(
+        rename(serialize = "Sunrise Hour"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub srise: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Sunset Hour"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sset: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Daylight Length"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub daylen: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Solar Noon Time"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub solnoon: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Civil Dawn"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub civdawn: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Civil Dusk"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub civdusk: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Nautical Dawn"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub nautdawn: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Nautical Dusk"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub nautdusk: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Astronomical Dawn"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub astrodawn: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Astronomical Dusk"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub astrodusk: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Sunrise Azimuth"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sriseaz: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Sunset Azimuth"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub ssetaz: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Solar Noon Altitude"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub noonalt: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Moonrise Hour"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mrise: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Moonset Hour"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mset: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Moon Transit Time"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mtransit: Option<NumOrStr>,
+    #[serde(
+        rename(serialize = "Moon Phase"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub mphase: Option<String>,
+    #[serde(
+        rename(serialize = "Moon Illumination"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub millum: Option<NumOrStr>,
+    /// Change to the next day's fitted value, minutes per day; only written
+    /// with `--change-rates`
+    #[serde(
+        rename(serialize = "Daylight Change (min/day)"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub daylen_change: Option<f64>,
+    #[serde(
+        rename(serialize = "Sunrise Change (min/day)"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub srise_change: Option<f64>,
+    #[serde(
+        rename(serialize = "Sunset Change (min/day)"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub sset_change: Option<f64>,
+    /// Whether the row was filled in by `--resample` rather than observed;
+    /// only written when resampling
+    #[serde(
+        rename(serialize = "Interpolated"),
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub interpolated: Option<bool>,
+}
+
+impl From<&Day> for XDay {
+    fn from(day: &Day) -> Self {
+        XDay {
+            yday: day.yday,
+            srise: day.srise.clone(),
+            sset: day.sset.clone(),
+            solnoon: day.solnoon.clone(),
+            daylen: day.daylen.clone(),
+            civdawn: day.civdawn.clone(),
+            civdusk: day.civdusk.clone(),
+            nautdawn: day.nautdawn.clone(),
+            nautdusk: day.nautdusk.clone(),
+            astrodawn: day.astrodawn.clone(),
+            astrodusk: day.astrodusk.clone(),
+            sriseaz: day.sriseaz.clone(),
+            ssetaz: day.ssetaz.clone(),
+            noonalt: day.noonalt.clone(),
+            mrise: day.mrise.clone(),
+            mset: day.mset.clone(),
+            mtransit: day.mtransit.clone(),
+            mphase: day.mphase.clone(),
+            millum: day.millum.clone(),
+            daylen_change: None,
+            srise_change: None,
+            sset_change: None,
+            interpolated: None,
+        }
+    }
+}
+
+/// The per-day quantities that get plotted and fitted.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Quantity {
+    Sunrise,
+    Sunset,
+    SolarNoon,
+    DayLength,
+    CivilDawn,
+    CivilDusk,
+    NauticalDawn,
+    NauticalDusk,
+    AstronomicalDawn,
+    AstronomicalDusk,
+    RiseAzimuth,
+    SetAzimuth,
+    NoonAltitude,
+    Moonrise,
+    Moonset,
+    MoonTransit,
+}
+
+/// What a quantity is measured in; charts only put like units on one axis.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Unit {
+    Hours,
+    Degrees,
+}
+
+impl Quantity {
+    pub const ALL: [Quantity; 16] = [
+        Quantity::Sunrise,
+        Quantity::Sunset,
+        Quantity::SolarNoon,
+        Quantity::DayLength,
+        Quantity::CivilDawn,
+        Quantity::CivilDusk,
+        Quantity::NauticalDawn,
+        Quantity::NauticalDusk,
+        Quantity::AstronomicalDawn,
+        Quantity::AstronomicalDusk,
+        Quantity::RiseAzimuth,
+        Quantity::SetAzimuth,
+        Quantity::NoonAltitude,
+        Quantity::Moonrise,
+        Quantity::Moonset,
+        Quantity::MoonTransit,
+    ];
+
+    pub fn label(&self) -> &'static str {
+        match self {
+            Quantity::Sunrise => "Sunrise",
+            Quantity::Sunset => "Sunset",
+            Quantity::SolarNoon => "Solar Noon",
+            Quantity::DayLength => "Day Length",
+            Quantity::CivilDawn => "Civil Dawn",
+            Quantity::CivilDusk => "Civil Dusk",
+            Quantity::NauticalDawn => "Nautical Dawn",
+            Quantity::NauticalDusk => "Nautical Dusk",
+            Quantity::AstronomicalDawn => "Astronomical Dawn",
+            Quantity::AstronomicalDusk => "Astronomical Dusk",
+            Quantity::RiseAzimuth => "Sunrise Azimuth",
+            Quantity::SetAzimuth => "Sunset Azimuth",
+            Quantity::NoonAltitude => "Solar Noon Altitude",
+            Quantity::Moonrise => "Moonrise",
+            Quantity::Moonset => "Moonset",
+            Quantity::MoonTransit => "Moon Transit",
+        }
+    }
+
+    /// The record field holding the quantity, e.g. "srise".
+    pub fn key(&self) -> &'static str {
+        match self {
+            Quantity::Sunrise => "srise",
+            Quantity::Sunset => "sset",
+            Quantity::SolarNoon => "solnoon",
+            Quantity::DayLength => "daylen",
+            Quantity::CivilDawn => "civdawn",
+            Quantity::CivilDusk => "civdusk",
+            Quantity::NauticalDawn => "nautdawn",
+            Quantity::NauticalDusk => "nautdusk",
+            Quantity::AstronomicalDawn => "astrodawn",
+            Quantity::AstronomicalDusk => "astrodusk",
+            Quantity::RiseAzimuth => "sriseaz",
+            Quantity::SetAzimuth => "ssetaz",
+            Quantity::NoonAltitude => "noonalt",
+            Quantity::Moonrise => "mrise",
+            Quantity::Moonset => "mset",
+            Quantity::MoonTransit => "mtransit",
+        }
+    }
+
+    /// A quantity by its key ("srise") or its label in kebab case
+    /// ("solar-noon").
+    pub fn parse(name: &str) -> Option<Self> {
+        let name = name.trim().to_lowercase();
+        Quantity::ALL.into_iter().find(|quantity| {
+            quantity.key() == name || quantity.label().to_lowercase().replace(' ', "-") == name
+        })
+    }
+
+    pub fn unit(&self) -> Unit {
+        match self {
+            Quantity::RiseAzimuth | Quantity::SetAzimuth | Quantity::NoonAltitude => Unit::Degrees,
+            _ => Unit::Hours,
+        }
+    }
+
+    pub fn get<'a>(&self, day: &'a Day) -> Option<&'a NumOrStr> {
+        match self {
+            Quantity::Sunrise => day.srise.as_ref(),
+            Quantity::Sunset => day.sset.as_ref(),
+            Quantity::SolarNoon => day.solnoon.as_ref(),
+            Quantity::DayLength => day.daylen.as_ref(),
+            Quantity::CivilDawn => day.civdawn.as_ref(),
+            Quantity::CivilDusk => day.civdusk.as_ref(),
+            Quantity::NauticalDawn => day.nautdawn.as_ref(),
+            Quantity::NauticalDusk => day.nautdusk.as_ref(),
+            Quantity::AstronomicalDawn => day.astrodawn.as_ref(),
+            Quantity::AstronomicalDusk => day.astrodusk.as_ref(),
+            Quantity::RiseAzimuth => day.sriseaz.as_ref(),
+            Quantity::SetAzimuth => day.ssetaz.as_ref(),
+            Quantity::NoonAltitude => day.noonalt.as_ref(),
+            Quantity::Moonrise => day.mrise.as_ref(),
+            Quantity::Moonset => day.mset.as_ref(),
+            Quantity::MoonTransit => day.mtransit.as_ref(),
+        }
+    }
+
+    pub fn get_mut<'a>(&self, day: &'a mut Day) -> &'a mut Option<NumOrStr> {
+        match self {
+            Quantity::Sunrise => &mut day.srise,
+            Quantity::Sunset => &mut day.sset,
+            Quantity::SolarNoon => &mut day.solnoon,
+            Quantity::DayLength => &mut day.daylen,
+            Quantity::CivilDawn => &mut day.civdawn,
+            Quantity::CivilDusk => &mut day.civdusk,
+            Quantity::NauticalDawn => &mut day.nautdawn,
+            Quantity::NauticalDusk => &mut day.nautdusk,
+            Quantity::AstronomicalDawn => &mut day.astrodawn,
+            Quantity::AstronomicalDusk => &mut day.astrodusk,
+            Quantity::RiseAzimuth => &mut day.sriseaz,
+            Quantity::SetAzimuth => &mut day.ssetaz,
+            Quantity::NoonAltitude => &mut day.noonalt,
+            Quantity::Moonrise => &mut day.mrise,
+            Quantity::Moonset => &mut day.mset,
+            Quantity::MoonTransit => &mut day.mtransit,
+        }
+    }
+
+    /// Whether the quantity follows a yearly sine. Moon times slip by about
+    /// 50 minutes a day, wrap around midnight and skip a day each lunation,
+    /// so they are neither fitted nor joined across those breaks.
+    pub fn sinusoidal(&self) -> bool {
+        !matches!(
+            self,
+            Quantity::Moonrise | Quantity::Moonset | Quantity::MoonTransit
+        )
+    }
+
+    /// Whether the quantity is a time of day on the local clock, which
+    /// daylight saving time moves.
+    pub fn on_clock(&self) -> bool {
+        self.unit() == Unit::Hours && *self != Quantity::DayLength
+    }
+
+    /// Number of full sine periods per year; solar noon (the equation of
+    /// time) swings twice a year.
+    pub fn period_mul_2pi(&self) -> f64 {
+        match self {
+            Quantity::SolarNoon => 2.0,
+            _ => 1.0,
+        }
+    }
+}
+
+/// A dataset file: the fetcher writes the days under a location header,
+/// hand-made files may be a bare list of days.
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+pub enum DayFile<T> {
+    Located { location: AstroInfo, days: Vec<T> },
+    Bare(Vec<T>),
+}
+
+impl<T> DayFile<T> {
+    pub fn new(location: Option<AstroInfo>, days: Vec<T>) -> Self {
+        match location {
+            Some(location) => DayFile::Located { location, days },
+            None => DayFile::Bare(days),
+        }
+    }
+}
+
+/// A quantity's value as a function of the day number.
+pub(crate) type Curve<'a> = Box<dyn Fn(f64) -> f64 + 'a>;
+
+/// How `--resample` fills in the days between observations.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Resample {
+    /// The curve fitted to each quantity (see `--model`)
+    Model,
+    /// A periodic cubic spline through each quantity's samples
+    Spline,
+}
+
+/// A labelled set of `Day` records read from one input file, together with
+/// the curve fitted to each quantity.
+pub struct Dataset {
+    pub label: String,
+    /// Where the days were fetched for, from the file header
+    pub location: Option<AstroInfo>,
+    pub days: Vec<Day>,
+    pub fits: Vec<(Quantity, Fitment)>,
+    /// `(quantity, day number)` of samples flagged by a robust fit
+    pub outliers: Vec<(Quantity, u64)>,
+    /// Intervals of the sine fits to shade a prediction band around
+    pub bands: Vec<(Quantity, SineIntervals)>,
+    /// Year of the earliest day; day numbers count from its 1 January
+    pub start_year: u64,
+}
+
+impl Dataset {
+    pub fn load(path: &Path, label: String) -> Self {
+        let input = fs::read_to_string(path).expect("Unable to read file");
+        Self::parse(&input, label)
+    }
+
+    /// A dataset from the text of a dataset file.
+    pub fn parse(input: &str, label: String) -> Self {
+        match serde_json::from_str::<DayFile<Day>>(input).expect("Unable to parse JSON") {
+            DayFile::Located { location, days } => Self::located(label, Some(location), days),
+            DayFile::Bare(days) => Self::located(label, None, days),
+        }
+    }
+
+    /// A dataset of days without a location header.
+    pub fn from_days(label: String, days: Vec<Day>) -> Self {
+        Self::located(label, None, days)
+    }
+
+    fn located(label: String, location: Option<AstroInfo>, mut days: Vec<Day>) -> Self {
+        for day in &mut days {
+            day.normalize();
+
+            if day.m == 0 || day.d == 0 || day.d > days_in_month(day.y, day.m) {
+                eprintln!(
+                    "{:04}-{:02}-{:02}: not a calendar date; keeping yday {}",
+                    day.y, day.m, day.d, day.yday
+                );
+                continue;
+            }
+            let yday = day_of_year(day.y, day.m, day.d);
+            if day.yday != yday {
+                eprintln!(
+                    "{:04}-{:02}-{:02}: yday {} given, {} by the calendar; using {}",
+                    day.y, day.m, day.d, day.yday, yday, yday
+                );
+                day.yday = yday;
+            }
+        }
+
+        let start_year = days.iter().map(|day| day.y).min().unwrap_or_default();
+        let mut dataset = Dataset {
+            label,
+            location,
+            days: Vec::new(),
+            fits: Vec::new(),
+            outliers: Vec::new(),
+            bands: Vec::new(),
+            start_year,
+        };
+        days.sort_by_key(|day| dataset.day_number(day));
+        dataset.days = days;
+        dataset
+    }
+
+    /// Days since 1 January of `start_year`, counting it as day 1: the day
+    /// of the year for single-year datasets, and continuing past 365/366
+    /// for ones that span several years.
+    pub fn day_number(&self, day: &Day) -> u64 {
+        (self.start_year..day.y.max(self.start_year))
+            .map(days_in_year)
+            .sum::<u64>()
+            + day.yday
+    }
+
+    /// The date of a day number.
+    pub fn date(&self, day_number: u64) -> (u64, u64, u64) {
+        let (mut y, mut m, mut d) = (self.start_year, 1, day_number);
+        while d > days_in_year(y) {
+            d -= days_in_year(y);
+            y += 1;
+        }
+        while d > days_in_month(y, m) {
+            d -= days_in_month(y, m);
+            m += 1;
+        }
+        (y, m, d)
+    }
+
+    /// Length of the day axis: the days in the years the dataset spans.
+    pub fn axis_days(&self) -> u64 {
+        let end_year = self.days.iter().map(|day| day.y).max().unwrap_or_default();
+        (self.start_year..=end_year.max(self.start_year))
+            .map(days_in_year)
+            .sum()
+    }
+
+    /// `(value, day number)` pairs for the days that have the quantity.
+    pub fn series(&self, quantity: Quantity) -> Vec<(f64, u64)> {
+        self.days
+            .iter()
+            .filter_map(|day| {
+                quantity
+                    .get(day)
+                    .map(|v| (v.get_num(), self.day_number(day)))
+            })
+            .collect()
+    }
+
+    /// The series with the hour taken off the clock times on daylight
+    /// saving time: the days whose noon (or midpoint of sunrise and sunset)
+    /// sits well above the calculated apparent noon, as the physical model
+    /// finds them. Other quantities are returned as they are.
+    pub fn standard_time(&self, quantity: Quantity) -> Vec<(f64, u64)> {
+        let series = self.series(quantity);
+        if !quantity.on_clock() {
+            return series;
+        }
+        let utc_offset = self
+            .location
+            .as_ref()
+            .and_then(|location| location.utc_offset)
+            .unwrap_or(0.0);
+        let offsets: Vec<(u64, f64)> = self
+            .days
+            .iter()
+            .filter_map(|day| {
+                let noon = match (&day.solnoon, &day.srise, &day.sset) {
+                    (Some(noon), _, _) => noon.get_num(),
+                    (None, Some(srise), Some(sset)) => (srise.get_num() + sset.get_num()) / 2.0,
+                    _ => return None,
+                };
+                let julian_day = solar::julian_day(day.y, day.m, day.d) + 0.5 - utc_offset / 24.0;
+                let position = solar::position(julian_day);
+                Some((self.day_number(day), noon - apparent_noon(&position)))
+            })
+            .collect();
+        if offsets.is_empty() {
+            return series;
+        }
+        let dst = daylight_saving(
+            &offsets
+                .iter()
+                .map(|&(_, offset)| offset)
+                .collect::<Vec<_>>(),
+        );
+        let dst_days: Vec<u64> = offsets
+            .iter()
+            .zip(dst)
+            .filter(|(_, dst)| *dst)
+            .map(|(&(day, _), _)| day)
+            .collect();
+        series
+            .into_iter()
+            .map(|(value, day)| match dst_days.binary_search(&day) {
+                Ok(_) => (value - 1.0, day),
+                Err(_) => (value, day),
+            })
+            .collect()
+    }
+
+    /// The series split into runs that can be joined by lines: sinusoidal
+    /// quantities are one run, the others break at missing days and where
+    /// the time wraps around midnight.
+    pub fn segments(&self, quantity: Quantity) -> Vec<Vec<(f64, u64)>> {
+        let series = self.series(quantity);
+        if quantity.sinusoidal() {
+            return vec![series];
+        }
+
+        let mut segments: Vec<Vec<(f64, u64)>> = Vec::new();
+        let mut last: Option<(f64, u64)> = None;
+        for (value, yday) in series {
+            let joined = last.is_some_and(|(last_value, last_yday)| {
+                yday == last_yday + 1 && (value - last_value).abs() < 12.0
+            });
+            match segments.last_mut() {
+                Some(segment) if joined => segment.push((value, yday)),
+                _ => segments.push(vec![(value, yday)]),
+            }
+            last = Some((value, yday));
+        }
+        segments
+    }
+
+    /// Whether a robust fit flagged the quantity on that day.
+    pub fn is_outlier(&self, quantity: Quantity, day_number: u64) -> bool {
+        self.outliers.contains(&(quantity, day_number))
+    }
+
+    /// A day in its spreadsheet form, numbered on the continuous axis,
+    /// without its outlying values if asked.
+    fn xday(&self, day: &Day, exclude_outliers: bool) -> XDay {
+        let number = self.day_number(day);
+        let flagged: Vec<Quantity> = self
+            .outliers
+            .iter()
+            .filter(|(_, day)| exclude_outliers && *day == number)
+            .map(|(quantity, _)| *quantity)
+            .collect();
+        if flagged.is_empty() {
+            return XDay {
+                yday: number,
+                ..XDay::from(day)
+            };
+        }
+
+        let mut day = day.clone();
+        for quantity in flagged {
+            *quantity.get_mut(&mut day) = None;
+        }
+        XDay {
+            yday: number,
+            ..XDay::from(&day)
+        }
+    }
+
+    /// The days in their spreadsheet form, numbered on the continuous axis.
+    pub fn xdays(&self, exclude_outliers: bool) -> Vec<XDay> {
+        self.days
+            .iter()
+            .map(|day| self.xday(day, exclude_outliers))
+            .collect()
+    }
+
+    /// One row for every day on the axis: the observed days as they are,
+    /// the others with each sinusoidal quantity interpolated. Moon times
+    /// are left out of interpolated rows. Excluded outliers are left out of
+    /// the spline too. The spline repeats every tropical year, as the
+    /// spline model does, so both agree over several years.
+    pub fn resampled(&self, method: Resample, exclude_outliers: bool) -> Vec<XDay> {
+        let curves: Vec<(Quantity, Curve)> = self
+            .quantities()
+            .into_iter()
+            .filter(Quantity::sinusoidal)
+            .filter_map(|quantity| {
+                let curve: Curve = match method {
+                    Resample::Model => {
+                        let fitment = self.fit(quantity)?;
+                        Box::new(move |x| fitment.eval(x))
+                    }
+                    Resample::Spline => {
+                        let points: Vec<(f64, f64)> = self
+                            .series(quantity)
+                            .into_iter()
+                            .filter(|&(_, day)| {
+                                !(exclude_outliers && self.is_outlier(quantity, day))
+                            })
+                            .map(|(value, day)| (day as f64, value))
+                            .collect();
+                        let spline = PeriodicSpline::interpolate(&points, YEAR)?;
+                        Box::new(move |x| spline.eval(x))
+                    }
+                };
+                Some((quantity, curve))
+            })
+            .collect();
+
+        let mut observed = self.days.iter().peekable();
+        (1..=self.axis_days())
+            .map(|number| {
+                while observed
+                    .next_if(|day| self.day_number(day) < number)
+                    .is_some()
+                {}
+                if let Some(day) = observed.next_if(|day| self.day_number(day) == number) {
+                    return XDay {
+                        interpolated: Some(false),
+                        ..self.xday(day, exclude_outliers)
+                    };
+                }
+
+                let (y, m, d) = self.date(number);
+                let mut day = Day {
+                    y,
+                    m,
+                    d,
+                    yday: day_of_year(y, m, d),
+                    ..Default::default()
+                };
+                for (quantity, curve) in &curves {
+                    *quantity.get_mut(&mut day) = Some(NumOrStr::Num(curve(number as f64)));
+                }
+                XDay {
+                    yday: number,
+                    interpolated: Some(true),
+                    ..XDay::from(&day)
+                }
+            })
+            .collect()
+    }
+
+    /// The days in their spreadsheet form, a bare list for json2csv;
+    /// resampled to one row per day, without outliers and with the change
+    /// rates of day length, sunrise and sunset if asked.
+    pub fn transformed(
+        &self,
+        resample: Option<Resample>,
+        exclude_outliers: bool,
+        change_rates: bool,
+    ) -> Vec<XDay> {
+        let mut days = match resample {
+            Some(method) => self.resampled(method, exclude_outliers),
+            None => self.xdays(exclude_outliers),
+        };
+        if change_rates {
+            for day in &mut days {
+                day.daylen_change = self.change_rate(Quantity::DayLength, day.yday);
+                day.srise_change = self.change_rate(Quantity::Sunrise, day.yday);
+                day.sset_change = self.change_rate(Quantity::Sunset, day.yday);
+            }
+        }
+        days
+    }
+
+    /// Minutes per day the quantity's fit changes from day `day_number` to
+    /// the next; `None` without a fit.
+    pub fn change_rate(&self, quantity: Quantity, day_number: u64) -> Option<f64> {
+        let fitment = self.fit(quantity)?;
+        let x = day_number as f64;
+        Some((fitment.eval(x + 1.0) - fitment.eval(x)) * 60.0)
+    }
+
+    /// Day axis description, with the position and UTC offset when known.
+    pub fn day_axis(&self) -> String {
+        match &self.location {
+            Some(location) => format!("Day ({})", location.position()),
+            None => "Day".to_string(),
+        }
+    }
+
+    /// The quantities present in at least one record, in `Quantity::ALL`
+    /// order.
+    pub fn quantities(&self) -> Vec<Quantity> {
+        Quantity::ALL
+            .into_iter()
+            .filter(|quantity| self.days.iter().any(|day| quantity.get(day).is_some()))
+            .collect()
+    }
+
+    /// The quantities present that are measured in `unit`.
+    pub fn quantities_in(&self, unit: Unit) -> Vec<Quantity> {
+        self.quantities()
+            .into_iter()
+            .filter(|quantity| quantity.unit() == unit)
+            .collect()
+    }
+
+    /// Fill missing sunrise/sunset azimuths and noon altitudes from the
+    /// local solar calculator.
+    pub fn fill_angles(&mut self, latitude: f64, longitude: f64) {
+        for day in &mut self.days {
+            // angles barely move with the hour, so local mean time will do
+            let sun = solar::solar_day(day.y, day.m, day.d, latitude, longitude, longitude / 15.0);
+            if day.sriseaz.is_none() {
+                day.sriseaz = sun.rise_azimuth.map(NumOrStr::Num);
+            }
+            if day.ssetaz.is_none() {
+                day.ssetaz = sun.set_azimuth.map(NumOrStr::Num);
+            }
+            if day.noonalt.is_none() {
+                day.noonalt = Some(NumOrStr::Num(sun.noon_altitude));
+            }
+        }
+    }
+
+    /// Min/max trackers for every sinusoidal quantity present.
+    pub fn fitters(&self) -> Vec<(Quantity, SineFitter)> {
+        self.quantities()
+            .into_iter()
+            .filter(Quantity::sinusoidal)
+            .map(|quantity| {
+                let mut fitter = SineFitter::new();
+                for (value, yday) in self.series(quantity) {
+                    fitter.update(value, yday);
+                }
+                (quantity, fitter)
+            })
+            .collect()
+    }
+
+    /// The curve shown as the quantity's fit.
+    pub fn fit(&self, quantity: Quantity) -> Option<&Fitment> {
+        self.fits_of(quantity).next()
+    }
+
+    /// All the curves fitted to the quantity, the one shown as its fit
+    /// first and the others drawn for comparison.
+    pub fn fits_of(&self, quantity: Quantity) -> impl Iterator<Item = &Fitment> {
+        self.fits
+            .iter()
+            .filter(move |(q, _)| *q == quantity)
+            .map(|(_, fitment)| fitment)
+    }
+
+    /// `(day, low, high)` of the 95% prediction band around the quantity's
+    /// fit over the days the dataset spans; empty without one.
+    pub fn prediction_band(&self, quantity: Quantity) -> Vec<(f64, f64, f64)> {
+        let (Some(fitment), Some((_, intervals))) = (
+            self.fit(quantity),
+            self.bands.iter().find(|(q, _)| *q == quantity),
+        ) else {
+            return Vec::new();
+        };
+        (0..self.axis_days())
+            .map(|x| {
+                let x = x as f64;
+                let (fitted, width) = (fitment.eval(x), intervals.prediction(x));
+                (x, fitted - width, fitted + width)
+            })
+            .collect()
+    }
+}
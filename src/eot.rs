@@ -0,0 +1,172 @@
+//! The equation of time read off the observed solar noons: the noon drifts
+//! through the year by the equation of time around a constant, the clock's
+//! offset from local mean solar time (the longitude within the time zone),
+//! plus an hour on daylight saving time. Taking both out leaves the
+//! equation of time, and set against the noon altitude, the analemma.
+
+use std::fmt;
+
+use crate::day::{Dataset, Quantity};
+use crate::fit::{apparent_noon, daylight_saving, FourierFitment, YEAR};
+use crate::format::clock;
+use crate::solar::{self, SolarPosition};
+
+/// The equation of time is two harmonics of the year to within a minute.
+const HARMONICS: usize = 2;
+
+/// Hours a noon may sit off the others, once daylight saving and the
+/// equation of time are taken out, before it is set aside as a stray
+/// (a mistyped hour, a day the clocks changed).
+const STRAY_HOURS: f64 = 0.25;
+
+#[derive(Debug, Clone)]
+pub struct EquationOfTime {
+    /// `(day number, minutes)` of each solar noon: apparent minus mean
+    /// solar time
+    pub samples: Vec<(u64, f64)>,
+    /// Clock time (standard time, hours) of local mean solar noon
+    pub mean_noon: f64,
+    /// Samples taken to be on daylight saving time and moved back an hour
+    pub dst_days: usize,
+    /// Samples set aside as on neither standard nor daylight saving time
+    pub stray_days: usize,
+    /// RMS difference from the solar calculator's equation of time, minutes
+    pub calculator_rms: f64,
+    /// `(date, minutes)` of the fitted curve's least and greatest value
+    pub least: (String, f64),
+    pub greatest: (String, f64),
+    /// Fit of the solar noons on standard time
+    noon: FourierFitment,
+}
+
+impl EquationOfTime {
+    /// From the dataset's solar noons; `None` with too few of them for the
+    /// fit (six distinct days).
+    pub fn derive(dataset: &Dataset) -> Option<Self> {
+        let utc_offset = dataset
+            .location
+            .as_ref()
+            .and_then(|location| location.utc_offset)
+            .unwrap_or(0.0);
+        let noons: Vec<(f64, u64, SolarPosition)> = dataset
+            .days
+            .iter()
+            .filter_map(|day| {
+                let noon = Quantity::SolarNoon.get(day)?.get_num();
+                let julian_day = solar::julian_day(day.y, day.m, day.d) + 0.5 - utc_offset / 24.0;
+                Some((noon, dataset.day_number(day), solar::position(julian_day)))
+            })
+            .collect();
+
+        // daylight saving shows against the calculated apparent noon
+        let offsets: Vec<f64> = noons
+            .iter()
+            .map(|(noon, _, position)| noon - apparent_noon(position))
+            .collect();
+        let dst = daylight_saving(&offsets);
+        let standard_offsets: Vec<f64> = offsets
+            .iter()
+            .zip(&dst)
+            .map(|(offset, &dst)| offset - f64::from(u8::from(dst)))
+            .collect();
+        let mut sorted = standard_offsets.clone();
+        sorted.sort_by(f64::total_cmp);
+        let median = *sorted.get(sorted.len() / 2)?;
+        let kept: Vec<usize> = (0..noons.len())
+            .filter(|&i| (standard_offsets[i] - median).abs() <= STRAY_HOURS)
+            .collect();
+        let standard: Vec<(f64, f64)> = kept
+            .iter()
+            .map(|&i| {
+                let (noon, day, _) = noons[i];
+                (day as f64, noon - f64::from(u8::from(dst[i])))
+            })
+            .collect();
+
+        // the fit's constant term is the mean over the year, over which the
+        // equation of time averages out
+        let noon = FourierFitment::fit(&standard, YEAR, HARMONICS)?;
+        let mean_noon = noon.coefficients[0];
+        let samples: Vec<(u64, f64)> = standard
+            .iter()
+            .map(|&(day, noon)| (day as u64, (mean_noon - noon) * 60.0))
+            .collect();
+        let squares: f64 = samples
+            .iter()
+            .zip(&kept)
+            .map(|(&(_, eot), &i)| (eot - noons[i].2.eot_minutes).powi(2))
+            .sum();
+
+        let mut equation = EquationOfTime {
+            samples,
+            mean_noon,
+            dst_days: kept.iter().filter(|&&i| dst[i]).count(),
+            stray_days: noons.len() - kept.len(),
+            calculator_rms: (squares / kept.len() as f64).sqrt(),
+            least: (String::new(), 0.0),
+            greatest: (String::new(), 0.0),
+            noon,
+        };
+        let curve: Vec<(u64, f64)> = (1..=YEAR as u64)
+            .map(|day| (day, equation.eval(day as f64)))
+            .collect();
+        let date = |&(day, minutes): &(u64, f64)| {
+            let (y, m, d) = dataset.date(day);
+            (format!("{:04}-{:02}-{:02}", y, m, d), minutes)
+        };
+        equation.least = curve.iter().min_by(|a, b| a.1.total_cmp(&b.1)).map(date)?;
+        equation.greatest = curve.iter().max_by(|a, b| a.1.total_cmp(&b.1)).map(date)?;
+        Some(equation)
+    }
+
+    /// The fitted equation of time on day `x`, minutes.
+    pub fn eval(&self, x: f64) -> f64 {
+        (self.mean_noon - self.noon.eval(x)) * 60.0
+    }
+
+    /// Degrees east, given the UTC offset of the clock's standard time.
+    pub fn longitude(&self, utc_offset: f64) -> f64 {
+        (12.0 + utc_offset - self.mean_noon) * 15.0
+    }
+
+    /// `(minutes, degrees, day number)` of the days with both a solar noon
+    /// and a noon altitude, in day order.
+    pub fn analemma(&self, dataset: &Dataset) -> Vec<(f64, f64, u64)> {
+        let altitudes = dataset.series(Quantity::NoonAltitude);
+        self.samples
+            .iter()
+            .filter_map(|&(day, minutes)| {
+                let (altitude, _) = altitudes.iter().find(|(_, d)| *d == day)?;
+                Some((minutes, *altitude, day))
+            })
+            .collect()
+    }
+}
+
+/// "-14.2 min (2024-02-11) to +16.4 min (2024-11-03), mean solar noon at
+/// 12:13 standard time, 15 days on DST, 1 stray day set aside, RMS 0.5 min
+/// from the solar calculator"
+impl fmt::Display for EquationOfTime {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "{:+.1} min ({}) to {:+.1} min ({}), mean solar noon at {} standard time, \
+             {} days on DST, ",
+            self.least.1,
+            self.least.0,
+            self.greatest.1,
+            self.greatest.0,
+            clock(self.mean_noon),
+            self.dst_days,
+        )?;
+        if self.stray_days > 0 {
+            let plural = if self.stray_days == 1 { "" } else { "s" };
+            write!(f, "{} stray day{} set aside, ", self.stray_days, plural)?;
+        }
+        write!(
+            f,
+            "RMS {:.1} min from the solar calculator",
+            self.calculator_rms
+        )
+    }
+}
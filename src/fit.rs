@@ -0,0 +1,346 @@
+use crate::day::Quantity;
+
+mod cv;
+mod fourier;
+mod interval;
+mod joint;
+mod physical;
+mod robust;
+mod spline;
+
+pub use cv::{Candidate, CrossValidation};
+pub use fourier::{FourierFitment, MAX_HARMONICS};
+pub use interval::SineIntervals;
+pub use joint::{Joint, JointFitment};
+pub use physical::PhysicalFit;
+pub(crate) use physical::{apparent_noon, daylight_saving};
+pub use robust::{Loss, RobustFit};
+pub use spline::{PeriodicSpline, SplineFitment};
+
+/// Days in a tropical year, the period of the sine model.
+pub const YEAR: f64 = 365.2422;
+
+/// Which model json2day fits to sunrise, sunset, noon and day length.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Model {
+    /// A sine per quantity from its extremes
+    Sine,
+    /// The sunrise equation, fitting latitude and clock offset
+    Physical,
+    /// A periodic smoothing spline per quantity, smoothed by
+    /// cross-validation
+    Spline,
+    /// A Fourier series of --harmonics harmonics per quantity, by least
+    /// squares
+    Fourier,
+    /// Whichever model cross-validates best, per quantity
+    Auto,
+}
+
+impl Model {
+    /// Whether the model covers `quantity`; the physical model only
+    /// predicts `PhysicalFit::QUANTITIES`.
+    pub fn covers(&self, quantity: Quantity) -> bool {
+        match self {
+            Model::Physical => PhysicalFit::QUANTITIES.contains(&quantity),
+            Model::Sine | Model::Spline | Model::Fourier | Model::Auto => true,
+        }
+    }
+}
+
+/// A `--model` argument: `MODEL` for every quantity, or `QUANTITY=MODEL`
+/// for one.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct ModelChoice {
+    pub quantity: Option<Quantity>,
+    pub model: Model,
+}
+
+impl ModelChoice {
+    pub fn parse(value: &str) -> Result<Self, String> {
+        let (quantity, model) = match value.split_once('=') {
+            Some((quantity, model)) => (
+                Some(
+                    Quantity::parse(quantity)
+                        .ok_or_else(|| format!("unknown quantity {:?}", quantity))?,
+                ),
+                model,
+            ),
+            None => (None, value),
+        };
+        let model = <Model as clap::ValueEnum>::from_str(model, true)?;
+        Ok(ModelChoice { quantity, model })
+    }
+
+    /// The models to fit to `quantity`, the first drawn as its fit and the
+    /// others for comparison: those chosen for it by name, else those
+    /// chosen for every quantity, else the sine; leaving out any that do not
+    /// cover it.
+    pub fn models_for(choices: &[ModelChoice], quantity: Quantity) -> Vec<Model> {
+        let named: Vec<Model> = choices
+            .iter()
+            .filter(|choice| choice.quantity == Some(quantity))
+            .map(|choice| choice.model)
+            .collect();
+        let chosen = if named.is_empty() {
+            choices
+                .iter()
+                .filter(|choice| choice.quantity.is_none())
+                .map(|choice| choice.model)
+                .collect()
+        } else {
+            named
+        };
+
+        let mut models: Vec<Model> = Vec::new();
+        for model in chosen {
+            if model.covers(quantity) && !models.contains(&model) {
+                models.push(model);
+            }
+        }
+        if models.is_empty() {
+            models.push(Model::Sine);
+        }
+        models
+    }
+}
+
+/// A fitted curve of one quantity over the year.
+#[derive(Debug, Clone)]
+pub enum Fitment {
+    Sine(SineFitment),
+    /// One of `PhysicalFit::QUANTITIES` from a physical fit
+    Physical(Quantity, PhysicalFit),
+    Spline(SplineFitment),
+    Fourier(FourierFitment),
+    /// Derived from the fits of two other quantities by `--joint`
+    Joint(JointFitment),
+}
+
+impl Fitment {
+    pub fn eval(&self, x: f64) -> f64 {
+        match self {
+            Fitment::Sine(fitment) => fitment.eval(x),
+            Fitment::Physical(quantity, fit) => fit
+                .eval(*quantity, x)
+                .expect("physical fit of a quantity it does not model"),
+            Fitment::Spline(fitment) => fitment.eval(x),
+            Fitment::Fourier(fitment) => fitment.eval(x),
+            Fitment::Joint(fitment) => fitment.eval(x),
+        }
+    }
+
+    /// Name of the model, as cross-validation prints it: "sine",
+    /// "fourier-2", ...
+    pub fn model(&self) -> String {
+        match self {
+            Fitment::Sine(_) => String::from("sine"),
+            Fitment::Physical(..) => String::from("physical"),
+            Fitment::Spline(_) => String::from("spline"),
+            Fitment::Fourier(fitment) => format!("fourier-{}", fitment.harmonics()),
+            Fitment::Joint(_) => String::from("joint"),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+pub struct SineFitment {
+    pub period_mul_2pi: f64,
+    pub a: f64,
+    pub b: f64,
+    pub c: f64,
+    pub d: f64,
+}
+
+impl Default for SineFitment {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SineFitment {
+    pub fn new() -> Self {
+        SineFitment {
+            period_mul_2pi: 0.0,
+            a: 0.0,
+            b: 0.0,
+            c: 0.0,
+            d: 0.0,
+        }
+    }
+
+    /// f(x) = A sin(Bx + C * period_mul_2pi * 2π/YEAR) + D
+    pub fn eval(&self, x: f64) -> f64 {
+        self.a
+            * (self.b * x + self.c * self.period_mul_2pi * 2.0 * std::f64::consts::PI / YEAR).sin()
+            + self.d
+    }
+}
+
+pub struct SineFitter {
+    pub min: f64,
+    pub min_day: u64,
+    pub max: f64,
+    pub max_day: u64,
+}
+
+impl Default for SineFitter {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SineFitter {
+    pub fn new() -> Self {
+        SineFitter {
+            min: f64::MAX,
+            min_day: 0,
+            max: f64::MIN,
+            max_day: 0,
+        }
+    }
+
+    pub fn update(&mut self, value: f64, yday: u64) {
+        if value < self.min {
+            self.min = value;
+            self.min_day = yday;
+        }
+        if value > self.max {
+            self.max = value;
+            self.max_day = yday;
+        }
+    }
+
+    pub fn get_midline(&self) -> f64 {
+        (self.min + self.max) / 2.0
+    }
+
+    pub fn fit(&self, data: &[(f64, u64)], period_mul_2pi: f64) -> SineFitment {
+        if data.is_empty() {
+            return SineFitment::new();
+        }
+        self.estimate(data, period_mul_2pi).0
+    }
+
+    /// How `fit` arrives at the sine, a line per parameter; empty without
+    /// data.
+    pub fn working(&self, data: &[(f64, u64)], period_mul_2pi: f64) -> String {
+        if data.is_empty() {
+            return String::new();
+        }
+        let (fitment, closest_day, shift) = self.estimate(data, period_mul_2pi);
+
+        format!(
+            "Magnitude = A = |{:.2} - {:.2}| / 2 = {:.2}\n\
+             Midline = D = ({:.2} + {:.2}) / 2 = {:.2}\n\
+             Period = {} = 2\u{03C0}/B ==> B = {:.0}\u{03C0} / {}\n\
+             Phase = C/B = {} ==> C = {}*2\u{03C0}/{}\n",
+            self.max,
+            self.min,
+            fitment.a,
+            self.max,
+            self.min,
+            fitment.d,
+            YEAR,
+            2.0 * period_mul_2pi,
+            YEAR,
+            closest_day,
+            shift,
+            YEAR
+        )
+    }
+
+    /// The fit without the working: the sine, the day closest to the
+    /// midline and the shift found from it before its sign is set.
+    fn estimate(&self, data: &[(f64, u64)], period_mul_2pi: f64) -> (SineFitment, u64, f64) {
+        let mut fitment = SineFitment::new();
+
+        //{
+        //    a: 1.0,
+        //    b: 2.0 * std::f64::consts::PI / 365.0,
+        //    c: 0.0,
+        //    d: self.get_midline(),
+        //};
+
+        fitment.period_mul_2pi = period_mul_2pi;
+        fitment.a = (self.max - self.min) / 2.0;
+        fitment.d = self.get_midline();
+        fitment.b = period_mul_2pi * 2.0 * std::f64::consts::PI / YEAR;
+
+        // find ydar closest to midline
+        let mut closest = data[0];
+        let mut closest_last_pt = data[0];
+        let mut closest_deriv = 0.0;
+        for d in data {
+            let deriv = (d.0 - closest_last_pt.0) / (d.1 - closest_last_pt.1) as f64;
+            closest_last_pt = *d;
+            if (d.0 - fitment.d).abs() < (closest.0 - fitment.d).abs() {
+                closest = *d;
+                closest_deriv = deriv;
+            }
+        }
+
+        // the curve repeats every YEAR, so for datasets spanning several
+        // years only the day's place within its year matters here
+        let mut shift = closest.1 as f64;
+        let within_year = shift % YEAR;
+        if (within_year > (YEAR / 2.0)) && (within_year < YEAR) {
+            //shift = shift - 365.0;
+            //shift = 365.0 - shift;
+            if closest_deriv > 0.00 {
+                shift -= YEAR / (2.0 * period_mul_2pi);
+            } else {
+                shift = YEAR / (2.0 * period_mul_2pi) - shift;
+            }
+        }
+
+        //eprintln!(
+        //    "Closest: {:?}, Shift: {}; deriv: {}",
+        //    closest, shift, closest_deriv
+        //);
+
+        fitment.c = shift * if closest_deriv >= 0.01 { -1.0 } else { 1.0 };
+
+        (fitment, closest.1, shift)
+    }
+
+    /// Fit `data` (sorted by day) from its own extremes, without printing
+    /// the working; the sine model as cross-validation refits it.
+    pub fn fit_quietly(data: &[(f64, u64)], period_mul_2pi: f64) -> Option<SineFitment> {
+        if data.is_empty() {
+            return None;
+        }
+        let mut fitter = SineFitter::new();
+        for &(value, day) in data {
+            fitter.update(value, day);
+        }
+        Some(fitter.estimate(data, period_mul_2pi).0)
+    }
+}
+
+/// Gaussian elimination with partial pivoting on a 3×3 system.
+fn solve3(mut matrix: [[f64; 3]; 3], mut rhs: [f64; 3]) -> Option<[f64; 3]> {
+    for column in 0..3 {
+        let pivot = (column..3)
+            .max_by(|&a, &b| matrix[a][column].abs().total_cmp(&matrix[b][column].abs()))?;
+        if matrix[pivot][column].abs() < 1e-12 {
+            return None;
+        }
+        matrix.swap(column, pivot);
+        rhs.swap(column, pivot);
+        let pivot_row = matrix[column];
+        for row in column + 1..3 {
+            let factor = matrix[row][column] / pivot_row[column];
+            for (entry, pivot) in matrix[row].iter_mut().zip(pivot_row).skip(column) {
+                *entry -= factor * pivot;
+            }
+            rhs[row] -= factor * rhs[column];
+        }
+    }
+    let mut solution = [0.0; 3];
+    for row in (0..3).rev() {
+        let known: f64 = (row + 1..3).map(|k| matrix[row][k] * solution[k]).sum();
+        solution[row] = (rhs[row] - known) / matrix[row][row];
+    }
+    Some(solution)
+}
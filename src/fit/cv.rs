@@ -0,0 +1,206 @@
+//! Cross-validation of the models against each other: each is refitted
+//! with a fold of the days held out and scored on how well it predicts
+//! them, so a model with more freedom only wins when it generalises.
+
+use std::fmt;
+
+use super::{FourierFitment, Model, PhysicalFit, SineFitter, SplineFitment, MAX_HARMONICS, YEAR};
+use crate::day::{Curve, Dataset, Day, Quantity, Unit};
+
+/// A model as cross-validation tries it.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Candidate {
+    Sine,
+    /// A Fourier series with this many harmonics
+    Fourier(usize),
+    Spline,
+    Physical,
+}
+
+impl Candidate {
+    pub fn model(&self) -> Model {
+        match self {
+            Candidate::Sine => Model::Sine,
+            Candidate::Fourier(_) => Model::Fourier,
+            Candidate::Spline => Model::Spline,
+            Candidate::Physical => Model::Physical,
+        }
+    }
+
+    /// The candidates for `quantity`: every model that covers it, with one
+    /// to `MAX_HARMONICS` harmonics for the Fourier series.
+    pub fn all(quantity: Quantity) -> Vec<Candidate> {
+        let mut candidates = vec![Candidate::Sine];
+        candidates.extend((1..=MAX_HARMONICS).map(Candidate::Fourier));
+        candidates.push(Candidate::Spline);
+        if Model::Physical.covers(quantity) {
+            candidates.push(Candidate::Physical);
+        }
+        candidates
+    }
+}
+
+/// "sine", "fourier-2", ...
+impl fmt::Display for Candidate {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            Candidate::Sine => write!(f, "sine"),
+            Candidate::Fourier(harmonics) => write!(f, "fourier-{}", harmonics),
+            Candidate::Spline => write!(f, "spline"),
+            Candidate::Physical => write!(f, "physical"),
+        }
+    }
+}
+
+/// Out-of-sample RMS error of each candidate for one quantity.
+#[derive(Debug, Clone)]
+pub struct CrossValidation {
+    pub quantity: Quantity,
+    /// Samples of the quantity
+    pub samples: usize,
+    /// Number of folds; as many as samples for leave-one-out
+    pub folds: usize,
+    /// Candidates that could be fitted on every fold, best first
+    pub scores: Vec<(Candidate, f64)>,
+}
+
+impl CrossValidation {
+    /// Score the candidates for `quantity` over `folds` folds (leave-one-out
+    /// when `None`); samples are dealt to the folds in turn, so each fold
+    /// spans the year. `None` with fewer than four samples.
+    pub fn run(dataset: &Dataset, quantity: Quantity, folds: Option<usize>) -> Option<Self> {
+        let series = dataset.series(quantity);
+        if series.len() < 4 {
+            return None;
+        }
+        let folds = folds.map_or(series.len(), |folds| folds.clamp(2, series.len()));
+
+        // the spline's smoothing is chosen once, on all the samples: choosing
+        // it again in every fold costs a cross-validation each
+        let points: Vec<(f64, f64)> = series
+            .iter()
+            .map(|&(value, day)| (day as f64, value))
+            .collect();
+        let lambda = SplineFitment::fit(&points, YEAR).map(|fitment| fitment.lambda);
+
+        let mut scores: Vec<(Candidate, f64)> = Candidate::all(quantity)
+            .into_iter()
+            .filter_map(|candidate| {
+                let fit =
+                    |training: &[(f64, u64)]| refit(dataset, quantity, candidate, lambda, training);
+                Some((candidate, score(&series, folds, fit)?))
+            })
+            .collect();
+        scores.sort_by(|a, b| a.1.total_cmp(&b.1));
+
+        Some(CrossValidation {
+            quantity,
+            samples: series.len(),
+            folds,
+            scores,
+        })
+    }
+
+    pub fn best(&self) -> Option<Candidate> {
+        self.scores.first().map(|(candidate, _)| *candidate)
+    }
+}
+
+/// "Cross-validation Sunrise (leave-one-out, 24 days): physical 0.61 min,
+/// fourier-3 6.13 min, ..."
+impl fmt::Display for CrossValidation {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let folds = if self.folds == self.samples {
+            String::from("leave-one-out")
+        } else {
+            format!("{}-fold", self.folds)
+        };
+        let scores: Vec<String> = self
+            .scores
+            .iter()
+            .map(|(candidate, rms)| match self.quantity.unit() {
+                Unit::Hours => format!("{} {:.2} min", candidate, rms * 60.0),
+                Unit::Degrees => format!("{} {:.2}\u{00B0}", candidate, rms),
+            })
+            .collect();
+        write!(
+            f,
+            "Cross-validation {} ({}, {} days): {}",
+            self.quantity.label(),
+            folds,
+            self.samples,
+            scores.join(", ")
+        )
+    }
+}
+
+/// RMS error on the held-out samples over all folds; `None` if the model
+/// cannot be fitted to one of them.
+fn score<'a>(
+    series: &[(f64, u64)],
+    folds: usize,
+    fit: impl Fn(&[(f64, u64)]) -> Option<Curve<'a>>,
+) -> Option<f64> {
+    let mut squares = 0.0;
+    for fold in 0..folds {
+        let (held_out, training): (Vec<_>, Vec<_>) = series
+            .iter()
+            .enumerate()
+            .partition(|(index, _)| index % folds == fold);
+        let training: Vec<(f64, u64)> = training.into_iter().map(|(_, &sample)| sample).collect();
+        let curve = fit(&training)?;
+        squares += held_out
+            .iter()
+            .map(|(_, &(value, day))| (value - curve(day as f64)).powi(2))
+            .sum::<f64>();
+    }
+    Some((squares / series.len() as f64).sqrt())
+}
+
+/// `candidate` fitted to the training samples of `quantity`, quietly; the
+/// spline at smoothing `lambda`.
+fn refit<'a>(
+    dataset: &'a Dataset,
+    quantity: Quantity,
+    candidate: Candidate,
+    lambda: Option<f64>,
+    training: &[(f64, u64)],
+) -> Option<Curve<'a>> {
+    let points: Vec<(f64, f64)> = training
+        .iter()
+        .map(|&(value, day)| (day as f64, value))
+        .collect();
+    match candidate {
+        Candidate::Sine => {
+            let fitment = SineFitter::fit_quietly(training, quantity.period_mul_2pi())?;
+            Some(Box::new(move |x| fitment.eval(x)))
+        }
+        Candidate::Fourier(harmonics) => {
+            let fitment = FourierFitment::fit(&points, YEAR, harmonics)?;
+            Some(Box::new(move |x| fitment.eval(x)))
+        }
+        Candidate::Spline => {
+            let fitment = SplineFitment::with_lambda(&points, YEAR, lambda?)?;
+            Some(Box::new(move |x| fitment.eval(x)))
+        }
+        Candidate::Physical => {
+            let days: Vec<Day> = dataset
+                .days
+                .iter()
+                .filter(|day| {
+                    let number = dataset.day_number(day);
+                    training
+                        .binary_search_by_key(&number, |&(_, day)| day)
+                        .is_ok()
+                })
+                .cloned()
+                .collect();
+            let utc_offset = dataset
+                .location
+                .as_ref()
+                .and_then(|location| location.utc_offset);
+            let fit = PhysicalFit::fit(&days, utc_offset)?;
+            Some(Box::new(move |x| fit.eval(quantity, x).unwrap_or(f64::NAN)))
+        }
+    }
+}
@@ -0,0 +1,96 @@
+//! Fourier series over the year: the sine plus its first few overtones,
+//! fitted by least squares, for the quantities a single sine does not
+//! quite follow (the equation of time's lopsided swing).
+
+use std::f64::consts::PI;
+use std::fmt;
+
+use super::spline::cholesky;
+
+/// Most harmonics cross-validation tries.
+pub const MAX_HARMONICS: usize = 4;
+
+#[derive(Debug, Clone)]
+pub struct FourierFitment {
+    pub period: f64,
+    /// The mean, then the sine and cosine coefficients of each harmonic
+    pub coefficients: Vec<f64>,
+    /// RMS of the residuals, in the quantity's unit
+    pub rms: f64,
+}
+
+impl FourierFitment {
+    /// Fit `(x, y)` samples with `harmonics` harmonics of `period`; `None`
+    /// unless there are more distinct days than coefficients.
+    pub fn fit(points: &[(f64, f64)], period: f64, harmonics: usize) -> Option<Self> {
+        let size = 2 * harmonics + 1;
+        let mut days: Vec<u64> = points.iter().map(|&(x, _)| x.round() as u64).collect();
+        days.sort_unstable();
+        days.dedup();
+        if days.len() <= size {
+            return None;
+        }
+
+        let mut normal = vec![vec![0.0; size]; size];
+        let mut rhs = vec![0.0; size];
+        for &(x, y) in points {
+            let row = terms(x, period, harmonics);
+            for i in 0..size {
+                for j in 0..size {
+                    normal[i][j] += row[i] * row[j];
+                }
+                rhs[i] += row[i] * y;
+            }
+        }
+        let coefficients = cholesky(normal)?.solve(&rhs);
+
+        let mut fitment = FourierFitment {
+            period,
+            coefficients,
+            rms: 0.0,
+        };
+        let squares: f64 = points
+            .iter()
+            .map(|&(x, y)| (y - fitment.eval(x)).powi(2))
+            .sum();
+        fitment.rms = (squares / points.len() as f64).sqrt();
+        Some(fitment)
+    }
+
+    pub fn harmonics(&self) -> usize {
+        self.coefficients.len() / 2
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        terms(x, self.period, self.harmonics())
+            .iter()
+            .zip(&self.coefficients)
+            .map(|(term, coefficient)| term * coefficient)
+            .sum()
+    }
+}
+
+/// 1, sin(ωx), cos(ωx), sin(2ωx), ... up to the given harmonic.
+fn terms(x: f64, period: f64, harmonics: usize) -> Vec<f64> {
+    let angle = 2.0 * PI * x / period;
+    std::iter::once(1.0)
+        .chain((1..=harmonics).flat_map(|k| {
+            let (sin, cos) = (k as f64 * angle).sin_cos();
+            [sin, cos]
+        }))
+        .collect()
+}
+
+/// "Fourier series, 2 harmonics, RMS 0.043"
+impl fmt::Display for FourierFitment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let harmonics = self.harmonics();
+        write!(
+            f,
+            "Fourier series, {} harmonic{}, RMS {:.3}",
+            harmonics,
+            if harmonics == 1 { "" } else { "s" },
+            self.rms
+        )
+    }
+}
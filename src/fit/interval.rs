@@ -0,0 +1,120 @@
+//! Approximate confidence intervals for a sine fit, about the sine shown:
+//! the covariance a least-squares fit would have there, linearised at its
+//! A, C and D and scaled by the residuals about it, and from it the
+//! uncertainty of the time predicted for any day. A sine placed by the
+//! extremes is not the least-squares optimum, so its scatter, and with it
+//! the intervals, come out wider than a refit's would.
+
+use std::fmt;
+
+use super::{solve3, SineFitment, YEAR};
+
+/// Standard errors to the half-width of a 95% interval (normal
+/// approximation).
+const CONFIDENCE_SCALE: f64 = 1.96;
+
+#[derive(Debug, Clone)]
+pub struct SineIntervals {
+    /// Residual standard deviation about the fit
+    pub sd: f64,
+    /// Standard error of the magnitude A
+    pub a_se: f64,
+    /// Standard error of the phase C, days
+    pub c_se: f64,
+    /// Standard error of the midline D
+    pub d_se: f64,
+    /// Covariance of (A, C, D)
+    covariance: [[f64; 3]; 3],
+    /// The sine the intervals are about
+    pub fitment: SineFitment,
+}
+
+impl SineIntervals {
+    /// Estimate from the samples the fit was made to; `None` with fewer
+    /// than four of them or a flat fit, which leave the phase undetermined.
+    pub fn estimate(fitment: &SineFitment, data: &[(f64, u64)]) -> Option<Self> {
+        if data.len() < 4 {
+            return None;
+        }
+        let mut information = [[0.0; 3]; 3];
+        let mut squares = 0.0;
+        for &(value, day) in data {
+            let gradient = gradient(fitment, day as f64);
+            for i in 0..3 {
+                for j in 0..3 {
+                    information[i][j] += gradient[i] * gradient[j];
+                }
+            }
+            squares += (value - fitment.eval(day as f64)).powi(2);
+        }
+        let variance = squares / (data.len() - 3) as f64;
+
+        // σ² (JᵀJ)⁻¹, a column at a time
+        let mut covariance = [[0.0; 3]; 3];
+        for column in 0..3 {
+            let mut unit = [0.0; 3];
+            unit[column] = 1.0;
+            let solution = solve3(information, unit)?;
+            for row in 0..3 {
+                covariance[row][column] = variance * solution[row];
+            }
+        }
+
+        Some(SineIntervals {
+            sd: variance.sqrt(),
+            a_se: covariance[0][0].sqrt(),
+            c_se: covariance[1][1].sqrt(),
+            d_se: covariance[2][2].sqrt(),
+            covariance,
+            fitment: fitment.clone(),
+        })
+    }
+
+    /// Half-width of the 95% interval of the fitted curve at day `x`.
+    pub fn confidence(&self, x: f64) -> f64 {
+        let gradient = gradient(&self.fitment, x);
+        let variance: f64 = (0..3)
+            .flat_map(|i| (0..3).map(move |j| (i, j)))
+            .map(|(i, j)| gradient[i] * self.covariance[i][j] * gradient[j])
+            .sum();
+        CONFIDENCE_SCALE * variance.max(0.0).sqrt()
+    }
+
+    /// Half-width of the 95% interval of a time observed on day `x`: the
+    /// curve's uncertainty plus the scatter about it.
+    pub fn prediction(&self, x: f64) -> f64 {
+        let curve = self.confidence(x) / CONFIDENCE_SCALE;
+        CONFIDENCE_SCALE * (curve.powi(2) + self.sd.powi(2)).sqrt()
+    }
+}
+
+/// ∂f/∂(A, C, D) of the fitted sine at day `x`.
+fn gradient(fitment: &SineFitment, x: f64) -> [f64; 3] {
+    let k = fitment.period_mul_2pi * 2.0 * std::f64::consts::PI / YEAR;
+    let angle = fitment.b * x + fitment.c * k;
+    [angle.sin(), fitment.a * k * angle.cos(), 1.0]
+}
+
+impl fmt::Display for SineIntervals {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let widths: Vec<f64> = (1..=YEAR as u64)
+            .map(|x| self.prediction(x as f64))
+            .collect();
+        let narrowest = widths.iter().copied().fold(f64::MAX, f64::min);
+        let widest = widths.iter().copied().fold(0.0, f64::max);
+        write!(
+            f,
+            "A = {:.3} ± {:.3}, C = {:.1} ± {:.1} days, D = {:.3} ± {:.3}, \
+             predictions ± {:.3}-{:.3} (approximate 95%, residual SD {:.3})",
+            self.fitment.a,
+            CONFIDENCE_SCALE * self.a_se,
+            self.fitment.c,
+            CONFIDENCE_SCALE * self.c_se,
+            self.fitment.d,
+            CONFIDENCE_SCALE * self.d_se,
+            narrowest,
+            widest,
+            self.sd
+        )
+    }
+}
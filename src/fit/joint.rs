@@ -0,0 +1,77 @@
+//! Sunrise, sunset, solar noon and day length fitted jointly: two of them
+//! fitted and the other two derived from those, so that the day length is
+//! always sunset - sunrise and noon halfway between.
+
+use std::fmt;
+
+use super::Fitment;
+use crate::day::Quantity;
+
+/// Which pair `--joint` fits.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Joint {
+    /// Fit solar noon and day length; sunrise and sunset follow
+    NoonLength,
+    /// Fit sunrise and sunset; solar noon and day length follow
+    RiseSet,
+}
+
+impl Joint {
+    /// The quantities fitted, in the order `JointFitment` takes them.
+    pub fn fitted(&self) -> [Quantity; 2] {
+        match self {
+            Joint::NoonLength => [Quantity::SolarNoon, Quantity::DayLength],
+            Joint::RiseSet => [Quantity::Sunrise, Quantity::Sunset],
+        }
+    }
+
+    pub fn derived(&self) -> [Quantity; 2] {
+        match self {
+            Joint::NoonLength => [Quantity::Sunrise, Quantity::Sunset],
+            Joint::RiseSet => [Quantity::SolarNoon, Quantity::DayLength],
+        }
+    }
+}
+
+/// One quantity derived from the fits of the other pair.
+#[derive(Debug, Clone)]
+pub struct JointFitment {
+    pub quantity: Quantity,
+    /// Fits of noon and day length for sunrise and sunset, or of sunrise
+    /// and sunset for noon and day length
+    pub from: Box<(Fitment, Fitment)>,
+}
+
+impl JointFitment {
+    /// `quantity` from the fits of the pair it is derived from, in the
+    /// order of `Joint::fitted`.
+    pub fn new(quantity: Quantity, first: Fitment, second: Fitment) -> Self {
+        JointFitment {
+            quantity,
+            from: Box::new((first, second)),
+        }
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        let (first, second) = (self.from.0.eval(x), self.from.1.eval(x));
+        match self.quantity {
+            Quantity::Sunrise => first - second / 2.0,
+            Quantity::Sunset => first + second / 2.0,
+            Quantity::SolarNoon => (first + second) / 2.0,
+            _ => second - first,
+        }
+    }
+}
+
+/// "solar noon - day length / 2"
+impl fmt::Display for JointFitment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let formula = match self.quantity {
+            Quantity::Sunrise => "solar noon - day length / 2",
+            Quantity::Sunset => "solar noon + day length / 2",
+            Quantity::SolarNoon => "(sunrise + sunset) / 2",
+            _ => "sunset - sunrise",
+        };
+        write!(f, "{}", formula)
+    }
+}
@@ -0,0 +1,303 @@
+//! The sunrise equation as a model: rise, set, noon and day length follow
+//! from the observer's latitude and how far their clock runs from local
+//! solar time, with declination and equation of time from the solar
+//! calculator. Fitting it to a dataset infers where it was observed.
+
+use std::fmt;
+
+use crate::day::{Day, Quantity};
+use crate::solar::{self, SolarPosition};
+
+/// Samples whose noon sits more than this many hours above the earliest
+/// ones are taken to be on daylight saving time.
+const DST_THRESHOLD: f64 = 0.5;
+
+/// Standard deviation (hours) of times rounded to the minute; the least
+/// noise assumed, so exact data still gets a region.
+const ROUNDING_SD: f64 = 1.0 / 60.0 / 3.4641;
+
+/// Bias (hours) that published times may share and that more days don't
+/// average out: truncated seconds, refraction, the publisher's algorithm.
+const SYSTEMATIC_SD: f64 = 1.0 / 60.0;
+
+/// Standard errors to the semi-axes of the 95% confidence ellipse
+/// (√χ² with 2 degrees of freedom).
+const CONFIDENCE_SCALE: f64 = 2.4477;
+
+#[derive(Debug, Clone)]
+pub struct PhysicalFit {
+    /// Degrees, north positive
+    pub latitude: f64,
+    /// Hours the clock (standard time) runs ahead of local mean solar time:
+    /// the UTC offset minus longitude/15
+    pub noon_offset: f64,
+    /// UTC offset of standard time, when known
+    pub utc_offset: Option<f64>,
+    /// Whether each sample's day was on daylight saving time, by day number
+    pub dst: Vec<(u64, bool)>,
+    /// RMS of the sunrise/sunset residuals in minutes
+    pub rms_minutes: f64,
+    /// Standard error of the latitude, degrees
+    pub latitude_se: f64,
+    /// Standard error of the noon offset, hours
+    pub noon_offset_se: f64,
+    year: u64,
+}
+
+/// Sunrise/sunset (and noon, when given) of one day.
+struct Sample {
+    /// Days since 1 January of the earliest year, that day being 1
+    day: u64,
+    position: SolarPosition,
+    srise: f64,
+    sset: f64,
+    noon: f64,
+}
+
+impl PhysicalFit {
+    /// The quantities the model predicts.
+    pub const QUANTITIES: [Quantity; 4] = [
+        Quantity::Sunrise,
+        Quantity::Sunset,
+        Quantity::SolarNoon,
+        Quantity::DayLength,
+    ];
+
+    /// Fit latitude and clock offset to the days with sunrise and sunset;
+    /// `None` with fewer than three of them. `utc_offset` only places the
+    /// result in longitude.
+    pub fn fit(days: &[Day], utc_offset: Option<f64>) -> Option<Self> {
+        let ut_noon = 12.0 - utc_offset.unwrap_or(0.0);
+        let year = days.iter().map(|day| day.y).min()?;
+        let new_year = solar::julian_day(year, 1, 1);
+        let samples: Vec<Sample> = days
+            .iter()
+            .filter_map(|day| {
+                let srise = day.srise.as_ref()?.get_num();
+                let sset = day.sset.as_ref()?.get_num();
+                let julian_day = solar::julian_day(day.y, day.m, day.d);
+                Some(Sample {
+                    day: (julian_day - new_year).round() as u64 + 1,
+                    position: solar::position(julian_day + ut_noon / 24.0),
+                    srise,
+                    sset,
+                    noon: day
+                        .solnoon
+                        .as_ref()
+                        .map_or((srise + sset) / 2.0, |noon| noon.get_num()),
+                })
+            })
+            .collect();
+        if samples.len() < 3 {
+            return None;
+        }
+
+        // noon against local apparent noon is the clock offset, plus an hour
+        // on daylight saving time
+        let offsets: Vec<f64> = samples
+            .iter()
+            .map(|sample| sample.noon - apparent_noon(&sample.position))
+            .collect();
+        let dst = daylight_saving(&offsets);
+        let standard: Vec<f64> = offsets
+            .iter()
+            .zip(&dst)
+            .map(|(offset, &dst)| offset - f64::from(u8::from(dst)))
+            .collect();
+        let noon_offset = quantile(&standard, 0.5);
+
+        // day length depends on latitude alone
+        let cost = |latitude: f64| -> f64 {
+            samples
+                .iter()
+                .map(|sample| {
+                    let length = solar::day_length(latitude, sample.position.declination);
+                    (length - (sample.sset - sample.srise)).powi(2)
+                })
+                .sum()
+        };
+        let coarse = (-178..=178)
+            .map(|step| step as f64 / 2.0)
+            .min_by(|a, b| cost(*a).total_cmp(&cost(*b)))?;
+        let latitude = golden_section(cost, coarse - 0.5, coarse + 0.5);
+
+        // linearised least squares, σ² / Σ(∂length/∂latitude)², plus the
+        // systematic error carried through the mean slope
+        let slope = |sample: &Sample| {
+            let length = |latitude| solar::day_length(latitude, sample.position.declination);
+            (length(latitude + 1e-4) - length(latitude - 1e-4)) / 2e-4
+        };
+        let n = samples.len() as f64;
+        let length_sd = (cost(latitude) / (n - 1.0)).sqrt().max(ROUNDING_SD);
+        let information: f64 = samples.iter().map(|sample| slope(sample).powi(2)).sum();
+        let latitude_se = ((length_sd.powi(2) + SYSTEMATIC_SD.powi(2) * n) / information).sqrt();
+
+        // the noon offset is a median: 1.2533 σ/√n, with σ from the MAD so
+        // a few mistyped noons don't blow it up
+        let deviations: Vec<f64> = standard
+            .iter()
+            .map(|offset| (offset - noon_offset).abs())
+            .collect();
+        let noon_sd = (1.4826 * quantile(&deviations, 0.5)).max(ROUNDING_SD);
+        let noon_offset_se = ((1.2533 * noon_sd).powi(2) / n + SYSTEMATIC_SD.powi(2)).sqrt();
+
+        let mut fit = PhysicalFit {
+            latitude,
+            noon_offset,
+            utc_offset,
+            dst: samples.iter().map(|sample| sample.day).zip(dst).collect(),
+            rms_minutes: 0.0,
+            latitude_se,
+            noon_offset_se,
+            year,
+        };
+
+        let squares: f64 = samples
+            .iter()
+            .map(|sample| {
+                let x = sample.day as f64;
+                let rise = fit.eval(Quantity::Sunrise, x).unwrap_or_default();
+                let set = fit.eval(Quantity::Sunset, x).unwrap_or_default();
+                (rise - sample.srise).powi(2) + (set - sample.sset).powi(2)
+            })
+            .sum();
+        fit.rms_minutes = (squares / (2 * samples.len()) as f64).sqrt() * 60.0;
+
+        Some(fit)
+    }
+
+    /// Degrees east, when the UTC offset is known.
+    pub fn longitude(&self) -> Option<f64> {
+        self.utc_offset
+            .map(|utc_offset| 15.0 * (utc_offset - self.noon_offset))
+    }
+
+    /// Semi-axes (latitude, longitude) in degrees of the 95% confidence
+    /// ellipse around the fitted position.
+    pub fn region(&self) -> (f64, f64) {
+        (
+            CONFIDENCE_SCALE * self.latitude_se,
+            CONFIDENCE_SCALE * 15.0 * self.noon_offset_se,
+        )
+    }
+
+    /// Whether a position lies in the confidence region, placing the fit in
+    /// longitude with `utc_offset`.
+    pub fn contains(&self, latitude: f64, longitude: f64, utc_offset: f64) -> bool {
+        let (latitude_axis, longitude_axis) = self.region();
+        let fitted_longitude = 15.0 * (utc_offset - self.noon_offset);
+        (latitude - self.latitude).powi(2) / latitude_axis.powi(2)
+            + (longitude - fitted_longitude).powi(2) / longitude_axis.powi(2)
+            <= 1.0
+    }
+
+    /// Days of the samples on daylight saving time.
+    pub fn dst_days(&self) -> usize {
+        self.dst.iter().filter(|(_, dst)| *dst).count()
+    }
+
+    /// The daylight saving shift on day `x`, as of the nearest sample.
+    fn dst_at(&self, x: f64) -> f64 {
+        let nearest = self
+            .dst
+            .iter()
+            .min_by(|a, b| (a.0 as f64 - x).abs().total_cmp(&(b.0 as f64 - x).abs()));
+        match nearest {
+            Some((_, true)) => 1.0,
+            _ => 0.0,
+        }
+    }
+
+    /// The model's value of `quantity` on day number `x`, on the clock of
+    /// the input (daylight saving time included); `None` for quantities it
+    /// does not predict.
+    pub fn eval(&self, quantity: Quantity, x: f64) -> Option<f64> {
+        let ut_noon = 12.0 - self.utc_offset.unwrap_or(0.0);
+        let julian_day = solar::julian_day(self.year, 1, 1) + x - 1.0 + ut_noon / 24.0;
+        let position = solar::position(julian_day);
+
+        let noon = apparent_noon(&position) + self.noon_offset + self.dst_at(x);
+        let length = solar::day_length(self.latitude, position.declination);
+        match quantity {
+            Quantity::Sunrise => Some(noon - length / 2.0),
+            Quantity::Sunset => Some(noon + length / 2.0),
+            Quantity::SolarNoon => Some(noon),
+            Quantity::DayLength => Some(length),
+            _ => None,
+        }
+    }
+}
+
+/// "latitude 47.62°N, longitude 122.41°W (UTC-8), 14 of 24 days on DST,
+/// RMS 1.4 min"
+impl fmt::Display for PhysicalFit {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        let hemisphere =
+            |value: f64, positive, negative| if value >= 0.0 { positive } else { negative };
+        write!(
+            f,
+            "latitude {:.2}°{}, ",
+            self.latitude.abs(),
+            hemisphere(self.latitude, 'N', 'S')
+        )?;
+        match (self.longitude(), self.utc_offset) {
+            (Some(longitude), Some(utc_offset)) => write!(
+                f,
+                "longitude {:.2}°{} (UTC{:+})",
+                longitude.abs(),
+                hemisphere(longitude, 'E', 'W'),
+                utc_offset
+            )?,
+            _ => write!(
+                f,
+                "{:.2}° {} of the time zone's meridian",
+                (15.0 * self.noon_offset).abs(),
+                hemisphere(-self.noon_offset, 'E', 'W')
+            )?,
+        }
+        write!(
+            f,
+            ", {} of {} days on DST, RMS {:.1} min",
+            self.dst_days(),
+            self.dst.len(),
+            self.rms_minutes
+        )
+    }
+}
+
+/// Which of the clock noons, given as offsets from local apparent noon
+/// (hours), are on daylight saving time: those well above the earliest.
+pub(crate) fn daylight_saving(offsets: &[f64]) -> Vec<bool> {
+    let base = quantile(offsets, 0.1);
+    offsets
+        .iter()
+        .map(|offset| offset - base > DST_THRESHOLD)
+        .collect()
+}
+
+/// Local apparent noon in local mean solar time, hours.
+pub(crate) fn apparent_noon(position: &SolarPosition) -> f64 {
+    12.0 - position.eot_minutes / 60.0
+}
+
+/// The `q` quantile of some values (nearest rank).
+fn quantile(values: &[f64], q: f64) -> f64 {
+    let mut sorted = values.to_vec();
+    sorted.sort_by(f64::total_cmp);
+    sorted[((sorted.len() - 1) as f64 * q).round() as usize]
+}
+
+/// Minimum of a unimodal function on `[a, b]`.
+fn golden_section(f: impl Fn(f64) -> f64, mut a: f64, mut b: f64) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    while b - a > 1e-6 {
+        let c = b - ratio * (b - a);
+        let d = a + ratio * (b - a);
+        if f(c) < f(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+    }
+    (a + b) / 2.0
+}
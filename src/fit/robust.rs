@@ -0,0 +1,151 @@
+//! Robust sine fitting: least squares on sin/cos of the day with
+//! iteratively reweighted residuals, so a mistyped time neither drags the
+//! curve nor the extremes the plain `SineFitter` starts from, and gets
+//! flagged as an outlier instead.
+
+use super::{solve3, SineFitment, YEAR};
+
+/// Huber's tuning constant, 95% efficient on normal errors.
+const HUBER_K: f64 = 1.345;
+
+/// Tukey's biweight tuning constant, 95% efficient on normal errors; samples
+/// beyond it get no weight.
+const TUKEY_C: f64 = 4.685;
+
+/// Residuals beyond this many robust standard deviations are outliers.
+const OUTLIER_CUTOFF: f64 = TUKEY_C;
+
+/// Least scale assumed (hours or degrees): a minute of rounding, so exact
+/// data does not turn every tiny residual into an outlier.
+const MIN_SCALE: f64 = 1.0 / 60.0;
+
+const ITERATIONS: usize = 50;
+
+/// How `--robust` weighs residuals.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Loss {
+    /// Quadratic near the fit, linear beyond: outliers still pull a little
+    Huber,
+    /// Tukey's biweight: far outliers are ignored altogether
+    Tukey,
+}
+
+#[derive(Debug, Clone)]
+pub struct RobustFit {
+    pub fitment: SineFitment,
+    /// Robust standard deviation of the residuals (1.4826 MAD)
+    pub scale: f64,
+    /// `(value, day)` of the samples beyond the outlier cutoff
+    pub outliers: Vec<(f64, u64)>,
+}
+
+impl RobustFit {
+    /// Fit `A sin(Bx + C) + D` with B fixed by `period_mul_2pi`; `None`
+    /// with fewer than three distinct days.
+    pub fn fit(data: &[(f64, u64)], period_mul_2pi: f64, loss: Loss) -> Option<Self> {
+        let b = period_mul_2pi * 2.0 * std::f64::consts::PI / YEAR;
+        let mut days: Vec<u64> = data.iter().map(|&(_, day)| day).collect();
+        days.sort_unstable();
+        days.dedup();
+        if days.len() < 3 {
+            return None;
+        }
+
+        let predict = |coefficients: &[f64; 3], day: u64| {
+            let x = b * day as f64;
+            coefficients[0] * x.sin() + coefficients[1] * x.cos() + coefficients[2]
+        };
+        let residuals = |coefficients: &[f64; 3]| -> Vec<f64> {
+            data.iter()
+                .map(|&(value, day)| value - predict(coefficients, day))
+                .collect()
+        };
+
+        // least squares to start, then Huber, then (for Tukey) the biweight
+        // from the Huber fit, which keeps it out of bad local minima
+        let mut coefficients = weighted_fit(data, b, &vec![1.0; data.len()])?;
+        let stages: &[Loss] = match loss {
+            Loss::Huber => &[Loss::Huber],
+            Loss::Tukey => &[Loss::Huber, Loss::Tukey],
+        };
+        for stage in stages {
+            for _ in 0..ITERATIONS {
+                let r = residuals(&coefficients);
+                let scale = robust_scale(&r);
+                let weights: Vec<f64> = r.iter().map(|r| weight(*stage, r / scale)).collect();
+                let next = weighted_fit(data, b, &weights)?;
+                let change = (0..3)
+                    .map(|i| (next[i] - coefficients[i]).abs())
+                    .fold(0.0, f64::max);
+                coefficients = next;
+                if change < 1e-9 {
+                    break;
+                }
+            }
+        }
+
+        let r = residuals(&coefficients);
+        let scale = robust_scale(&r);
+        let outliers = data
+            .iter()
+            .zip(&r)
+            .filter(|(_, r)| (*r / scale).abs() > OUTLIER_CUTOFF)
+            .map(|(&sample, _)| sample)
+            .collect();
+
+        // α sin(Bx) + β cos(Bx) = A sin(Bx + φ), φ = C period_mul_2pi 2π/YEAR
+        let (alpha, beta) = (coefficients[0], coefficients[1]);
+        let phase = beta.atan2(alpha);
+        Some(RobustFit {
+            fitment: SineFitment {
+                period_mul_2pi,
+                a: alpha.hypot(beta),
+                b,
+                c: phase * YEAR / (2.0 * std::f64::consts::PI * period_mul_2pi),
+                d: coefficients[2],
+            },
+            scale,
+            outliers,
+        })
+    }
+}
+
+fn weight(loss: Loss, u: f64) -> f64 {
+    match loss {
+        Loss::Huber if u.abs() <= HUBER_K => 1.0,
+        Loss::Huber => HUBER_K / u.abs(),
+        Loss::Tukey if u.abs() < TUKEY_C => (1.0 - (u / TUKEY_C).powi(2)).powi(2),
+        Loss::Tukey => 0.0,
+    }
+}
+
+/// 1.4826 times the median absolute residual, at least `MIN_SCALE`.
+fn robust_scale(residuals: &[f64]) -> f64 {
+    let mut absolute: Vec<f64> = residuals.iter().map(|r| r.abs()).collect();
+    absolute.sort_by(f64::total_cmp);
+    let middle = absolute.len() / 2;
+    let median = if absolute.len().is_multiple_of(2) {
+        (absolute[middle - 1] + absolute[middle]) / 2.0
+    } else {
+        absolute[middle]
+    };
+    (1.4826 * median).max(MIN_SCALE)
+}
+
+/// Weighted least squares for (α, β, D) in α sin(bx) + β cos(bx) + D; `None`
+/// if the weighted samples do not pin them down.
+fn weighted_fit(data: &[(f64, u64)], b: f64, weights: &[f64]) -> Option<[f64; 3]> {
+    let mut normal = [[0.0; 3]; 3];
+    let mut rhs = [0.0; 3];
+    for (&(value, day), &w) in data.iter().zip(weights) {
+        let x = b * day as f64;
+        let row = [x.sin(), x.cos(), 1.0];
+        for i in 0..3 {
+            for j in 0..3 {
+                normal[i][j] += w * row[i] * row[j];
+            }
+            rhs[i] += w * row[i] * value;
+        }
+    }
+    solve3(normal, rhs)
+}
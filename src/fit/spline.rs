@@ -0,0 +1,364 @@
+//! Periodic cubic splines: a smooth curve through a year's samples that
+//! joins up again from the last sample to the first, or, smoothed, a
+//! non-parametric model of a quantity to set against the sine fit.
+
+use std::fmt;
+
+/// Range of log10 λ searched by cross-validation; from practically
+/// interpolating to practically the mean.
+const LOG_LAMBDA_RANGE: (i32, i32) = (-4, 10);
+
+/// Knots closer than this (days) are merged into one, weighted by count.
+const KNOT_SPACING: f64 = 0.5;
+
+/// A cubic spline through `(x, y)` knots that repeats every `period`.
+#[derive(Debug, Clone)]
+pub struct PeriodicSpline {
+    period: f64,
+    /// Knots with x reduced into `[0, period)`, sorted
+    knots: Vec<(f64, f64)>,
+    /// Second derivative at each knot
+    curvature: Vec<f64>,
+}
+
+impl PeriodicSpline {
+    /// The spline through the points; `None` with fewer than three distinct
+    /// x in a period. Of points that coincide, the first is kept.
+    pub fn interpolate(points: &[(f64, f64)], period: f64) -> Option<Self> {
+        let mut knots: Vec<(f64, f64)> = points
+            .iter()
+            .map(|&(x, y)| (x.rem_euclid(period), y))
+            .collect();
+        knots.sort_by(|a, b| a.0.total_cmp(&b.0));
+        knots.dedup_by(|b, a| (b.0 - a.0).abs() < 1e-9);
+        let n = knots.len();
+        if n < 3 {
+            return None;
+        }
+
+        // interval widths, the last one wrapping around to the first knot
+        let width: Vec<f64> = (0..n)
+            .map(|i| match knots.get(i + 1) {
+                Some(next) => next.0 - knots[i].0,
+                None => knots[0].0 + period - knots[i].0,
+            })
+            .collect();
+        let slope = |i: usize| (knots[(i + 1) % n].1 - knots[i].1) / width[i];
+        let previous = |i: usize| (i + n - 1) % n;
+
+        // continuity of the first derivative at every knot:
+        // h[i-1] M[i-1] + 2 (h[i-1] + h[i]) M[i] + h[i] M[i+1] = 6 (s[i] - s[i-1])
+        let lower: Vec<f64> = (0..n).map(|i| width[previous(i)]).collect();
+        let diagonal: Vec<f64> = (0..n)
+            .map(|i| 2.0 * (width[previous(i)] + width[i]))
+            .collect();
+        let rhs: Vec<f64> = (0..n)
+            .map(|i| 6.0 * (slope(i) - slope(previous(i))))
+            .collect();
+        let curvature = solve_cyclic(&lower, &diagonal, &width, &rhs);
+
+        Some(PeriodicSpline {
+            period,
+            knots,
+            curvature,
+        })
+    }
+
+    /// The periodic cubic smoothing spline minimising
+    /// Σ w (y - g)² + λ ∫ g''², with weights from merging knots closer than
+    /// half a day. Returns it with the leave-one-out residuals.
+    fn smooth(points: &[(f64, f64)], period: f64, lambda: f64) -> Option<(Self, Vec<f64>)> {
+        let mut sorted: Vec<(f64, f64)> = points
+            .iter()
+            .map(|&(x, y)| (x.rem_euclid(period), y))
+            .collect();
+        sorted.sort_by(|a, b| a.0.total_cmp(&b.0));
+        let mut merged: Vec<(f64, f64, f64)> = Vec::new();
+        for (x, y) in sorted {
+            match merged.last_mut() {
+                Some((mx, my, w)) if x - *mx / *w < KNOT_SPACING => {
+                    *mx += x;
+                    *my += y;
+                    *w += 1.0;
+                }
+                _ => merged.push((x, y, 1.0)),
+            }
+        }
+        let knots: Vec<(f64, f64, f64)> = merged
+            .into_iter()
+            .map(|(x, y, w)| (x / w, y / w, w))
+            .collect();
+        let n = knots.len();
+        if n < 3 {
+            return None;
+        }
+
+        let width: Vec<f64> = (0..n)
+            .map(|i| match knots.get(i + 1) {
+                Some(next) => next.0 - knots[i].0,
+                None => knots[0].0 + period - knots[i].0,
+            })
+            .collect();
+        let previous = |i: usize| (i + n - 1) % n;
+
+        // Q maps values to the jumps in slope at each knot, R the second
+        // derivatives to the same (Green & Silverman, wrapped around); row k
+        // of Q is nonzero at k-1, k and k+1 only
+        let mut q = vec![[0.0; 3]; n];
+        let mut r = vec![vec![0.0; n]; n];
+        for j in 0..n {
+            let (before, after) = (width[previous(j)], width[j]);
+            q[previous(j)][2] += 1.0 / before;
+            q[j][1] -= 1.0 / before + 1.0 / after;
+            q[(j + 1) % n][0] += 1.0 / after;
+            r[j][j] += (before + after) / 3.0;
+            r[j][(j + 1) % n] += after / 6.0;
+            r[(j + 1) % n][j] += after / 6.0;
+        }
+        let columns = |k: usize| [previous(k), k, (k + 1) % n];
+
+        // (R + λ Qᵀ W⁻¹ Q) γ = Qᵀ y, g = y - λ W⁻¹ Q γ
+        let mut system = r;
+        for k in 0..n {
+            for (a, &i) in columns(k).iter().enumerate() {
+                for (b, &j) in columns(k).iter().enumerate() {
+                    system[i][j] += lambda * q[k][a] * q[k][b] / knots[k].2;
+                }
+            }
+        }
+        let factor = cholesky(system)?;
+        let mut qty = vec![0.0; n];
+        for k in 0..n {
+            for (a, &j) in columns(k).iter().enumerate() {
+                qty[j] += q[k][a] * knots[k].1;
+            }
+        }
+        let curvature = factor.solve(&qty);
+        let fitted: Vec<f64> = (0..n)
+            .map(|i| {
+                let qg: f64 = (0..3).map(|a| q[i][a] * curvature[columns(i)[a]]).sum();
+                knots[i].1 - lambda * qg / knots[i].2
+            })
+            .collect();
+
+        // hat matrix diagonal, 1 - λ/w (Q M⁻¹ Qᵀ)ᵢᵢ, for the leave-one-out
+        // residuals (y - g) / (1 - Aᵢᵢ)
+        let residuals = (0..n)
+            .map(|i| {
+                let mut row = vec![0.0; n];
+                for (a, &j) in columns(i).iter().enumerate() {
+                    row[j] += q[i][a];
+                }
+                let quadratic: f64 = factor.forward(&row).iter().map(|y| y * y).sum();
+                let leverage = 1.0 - lambda * quadratic / knots[i].2;
+                (knots[i].1 - fitted[i]) / (1.0 - leverage).max(1e-9)
+            })
+            .collect();
+
+        let spline = PeriodicSpline {
+            period,
+            knots: knots.iter().zip(fitted).map(|(k, g)| (k.0, g)).collect(),
+            curvature,
+        };
+        Some((spline, residuals))
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        let n = self.knots.len();
+        let first = self.knots[0].0;
+        let x = first + (x - first).rem_euclid(self.period);
+
+        let i = self.knots.partition_point(|knot| knot.0 <= x) - 1;
+        let j = (i + 1) % n;
+        let (xi, yi) = self.knots[i];
+        let (xj, yj) = match j {
+            0 => (self.knots[0].0 + self.period, self.knots[0].1),
+            _ => self.knots[j],
+        };
+        let (mi, mj) = (self.curvature[i], self.curvature[j]);
+
+        let h = xj - xi;
+        let (a, b) = (xj - x, x - xi);
+        mi * a.powi(3) / (6.0 * h)
+            + mj * b.powi(3) / (6.0 * h)
+            + (yi / h - mi * h / 6.0) * a
+            + (yj / h - mj * h / 6.0) * b
+    }
+}
+
+/// A periodic smoothing spline fitted to a quantity, with the smoothing
+/// chosen by leave-one-out cross-validation.
+#[derive(Debug, Clone)]
+pub struct SplineFitment {
+    pub spline: PeriodicSpline,
+    /// The smoothing parameter λ
+    pub lambda: f64,
+    /// RMS of the leave-one-out residuals, in the quantity's unit
+    pub cv_rms: f64,
+}
+
+impl SplineFitment {
+    /// Fit `(x, y)` samples with period `period`; `None` with fewer than
+    /// three distinct days.
+    pub fn fit(points: &[(f64, f64)], period: f64) -> Option<Self> {
+        let score = |log_lambda: f64| -> Option<f64> {
+            let (_, residuals) = PeriodicSpline::smooth(points, period, 10f64.powf(log_lambda))?;
+            let squares: f64 = residuals.iter().map(|r| r * r).sum();
+            Some((squares / residuals.len() as f64).sqrt())
+        };
+
+        let coarse = (LOG_LAMBDA_RANGE.0..=LOG_LAMBDA_RANGE.1)
+            .map(f64::from)
+            .filter_map(|log_lambda| Some((log_lambda, score(log_lambda)?)))
+            .min_by(|a, b| a.1.total_cmp(&b.1))?
+            .0;
+        let log_lambda = golden_section(
+            |log_lambda| score(log_lambda).unwrap_or(f64::MAX),
+            coarse - 1.0,
+            coarse + 1.0,
+        );
+
+        Self::with_lambda(points, period, 10f64.powf(log_lambda))
+    }
+
+    /// Fit `(x, y)` samples with period `period` at a given λ, as
+    /// cross-validation refits the spline chosen on all of them; `None`
+    /// with fewer than three distinct days.
+    pub fn with_lambda(points: &[(f64, f64)], period: f64, lambda: f64) -> Option<Self> {
+        let (spline, residuals) = PeriodicSpline::smooth(points, period, lambda)?;
+        let squares: f64 = residuals.iter().map(|r| r * r).sum();
+        Some(SplineFitment {
+            spline,
+            lambda,
+            cv_rms: (squares / residuals.len() as f64).sqrt(),
+        })
+    }
+
+    pub fn eval(&self, x: f64) -> f64 {
+        self.spline.eval(x)
+    }
+}
+
+/// "periodic spline, λ = 3.2e3, leave-one-out RMS 0.021"
+impl fmt::Display for SplineFitment {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(
+            f,
+            "periodic spline, \u{03BB} = {:.2e}, leave-one-out RMS {:.3}",
+            self.lambda, self.cv_rms
+        )
+    }
+}
+
+/// Minimum of a unimodal function on `[a, b]`, to 0.01.
+fn golden_section(f: impl Fn(f64) -> f64, mut a: f64, mut b: f64) -> f64 {
+    let ratio = (5f64.sqrt() - 1.0) / 2.0;
+    while b - a > 0.01 {
+        let c = b - ratio * (b - a);
+        let d = a + ratio * (b - a);
+        if f(c) < f(d) {
+            b = d;
+        } else {
+            a = c;
+        }
+    }
+    (a + b) / 2.0
+}
+
+/// Lower-triangular factor of a symmetric positive definite matrix, with
+/// the column of each row's first nonzero entry.
+pub(super) struct Cholesky(Vec<Vec<f64>>, Vec<usize>);
+
+/// `None` if the matrix is not positive definite. The factor fills in only
+/// within the envelope of each row's first nonzero entry, which keeps the
+/// cyclic band of the smoothing spline's system cheap.
+pub(super) fn cholesky(mut matrix: Vec<Vec<f64>>) -> Option<Cholesky> {
+    let n = matrix.len();
+    let first: Vec<usize> = (0..n)
+        .map(|i| (0..i).find(|&j| matrix[i][j] != 0.0).unwrap_or(i))
+        .collect();
+    for i in 0..n {
+        for j in first[i]..=i {
+            let start = first[i].max(first[j]);
+            let dot: f64 = (start..j).map(|k| matrix[i][k] * matrix[j][k]).sum();
+            if j < i {
+                matrix[i][j] = (matrix[i][j] - dot) / matrix[j][j];
+            } else {
+                let diagonal = matrix[i][i] - dot;
+                if diagonal <= 0.0 {
+                    return None;
+                }
+                matrix[i][i] = diagonal.sqrt();
+            }
+        }
+    }
+    Some(Cholesky(matrix, first))
+}
+
+impl Cholesky {
+    pub(super) fn solve(&self, rhs: &[f64]) -> Vec<f64> {
+        let (l, first) = (&self.0, &self.1);
+        let mut y = self.forward(rhs);
+        // Lᵀ column by column, so only the envelope is visited
+        for i in (0..y.len()).rev() {
+            y[i] /= l[i][i];
+            for k in first[i]..i {
+                y[k] -= l[i][k] * y[i];
+            }
+        }
+        y
+    }
+
+    /// L⁻¹ `rhs`, whose squared norm is `rhs`ᵀ M⁻¹ `rhs`.
+    fn forward(&self, rhs: &[f64]) -> Vec<f64> {
+        let (l, first) = (&self.0, &self.1);
+        let mut y = rhs.to_vec();
+        for i in 0..y.len() {
+            y[i] = (y[i] - (first[i]..i).map(|k| l[i][k] * y[k]).sum::<f64>()) / l[i][i];
+        }
+        y
+    }
+}
+
+/// Solve a tridiagonal system with corner entries: row i reads
+/// `lower[i] x[i-1] + diagonal[i] x[i] + upper[i] x[i+1] = rhs[i]`, indices
+/// wrapping around (Sherman-Morrison on the Thomas algorithm).
+fn solve_cyclic(lower: &[f64], diagonal: &[f64], upper: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let n = diagonal.len();
+    let (alpha, beta) = (upper[n - 1], lower[0]);
+    let gamma = -diagonal[0];
+
+    let mut modified = diagonal.to_vec();
+    modified[0] -= gamma;
+    modified[n - 1] -= alpha * beta / gamma;
+
+    let mut x = solve_tridiagonal(lower, &modified, upper, rhs);
+    let mut u = vec![0.0; n];
+    u[0] = gamma;
+    u[n - 1] = alpha;
+    let z = solve_tridiagonal(lower, &modified, upper, &u);
+
+    let fact = (x[0] + beta * x[n - 1] / gamma) / (1.0 + z[0] + beta * z[n - 1] / gamma);
+    for (x, z) in x.iter_mut().zip(z) {
+        *x -= fact * z;
+    }
+    x
+}
+
+/// The Thomas algorithm; `lower[0]` and `upper[n-1]` are ignored.
+fn solve_tridiagonal(lower: &[f64], diagonal: &[f64], upper: &[f64], rhs: &[f64]) -> Vec<f64> {
+    let n = diagonal.len();
+    let mut c = vec![0.0; n];
+    let mut d = vec![0.0; n];
+    c[0] = upper[0] / diagonal[0];
+    d[0] = rhs[0] / diagonal[0];
+    for i in 1..n {
+        let denominator = diagonal[i] - lower[i] * c[i - 1];
+        c[i] = upper[i] / denominator;
+        d[i] = (rhs[i] - lower[i] * d[i - 1]) / denominator;
+    }
+    for i in (0..n - 1).rev() {
+        d[i] -= c[i] * d[i + 1];
+    }
+    d
+}
@@ -0,0 +1,13 @@
+//! Formatting shared by the reports.
+
+/// "07:57" for 7.95 hours, "-00:30" for -0.5.
+pub fn clock(hours: f64) -> String {
+    let minutes = (hours * 60.0).round() as i64;
+    let sign = if minutes < 0 { "-" } else { "" };
+    format!(
+        "{}{:02}:{:02}",
+        sign,
+        minutes.abs() / 60,
+        minutes.abs() % 60
+    )
+}
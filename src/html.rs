@@ -0,0 +1,148 @@
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+
+use crate::day::{Dataset, Quantity, Unit};
+use crate::plot::{location_color, quantity_color, quantity_dash};
+
+/// Everything the page script needs, embedded as JSON.
+#[derive(Serialize)]
+struct Page<'a> {
+    caption: &'a str,
+    day_axis: String,
+    /// Length of the day axis
+    days: u64,
+    series: Vec<Series>,
+}
+
+/// One toggleable series: the observed samples of a quantity at a location,
+/// plus its fitted curve.
+#[derive(Serialize)]
+struct Series {
+    location: String,
+    quantity: &'static str,
+    color: String,
+    dash: Option<String>,
+    points: Vec<Point>,
+    fit: Vec<(f64, f64)>,
+    /// Curves of further models, drawn dashed for comparison
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    alternatives: Vec<Vec<(f64, f64)>>,
+    /// `(day, low, high)` of the prediction band around the fit
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    band: Vec<(f64, f64, f64)>,
+}
+
+#[derive(Serialize)]
+struct Point {
+    date: String,
+    day: u64,
+    value: f64,
+    fitted: Option<f64>,
+    residual: Option<f64>,
+    /// Starts a new run: the line from the previous sample is not drawn
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    gap: bool,
+    /// Flagged as an outlier by the robust fit
+    #[serde(skip_serializing_if = "std::ops::Not::not")]
+    outlier: bool,
+}
+
+const TEMPLATE: &str = include_str!("html/chart.html");
+
+fn build_series(index: usize, multiple: bool, dataset: &Dataset, quantity: Quantity) -> Series {
+    let fitment = dataset.fit(quantity);
+    let color = if multiple {
+        location_color(index)
+    } else {
+        quantity_color(quantity)
+    };
+
+    let run_starts: Vec<u64> = dataset
+        .segments(quantity)
+        .iter()
+        .skip(1)
+        .map(|segment| segment[0].1)
+        .collect();
+
+    let points = dataset
+        .days
+        .iter()
+        .filter_map(|day| {
+            let value = quantity.get(day)?.get_num();
+            let number = dataset.day_number(day);
+            let fitted = fitment.map(|fitment| fitment.eval(number as f64));
+            Some(Point {
+                date: format!("{:04}-{:02}-{:02}", day.y, day.m, day.d),
+                day: number,
+                value,
+                fitted,
+                residual: fitted.map(|fitted| value - fitted),
+                gap: run_starts.contains(&number),
+                outlier: dataset.is_outlier(quantity, number),
+            })
+        })
+        .collect();
+
+    let mut curves = dataset.fits_of(quantity).map(|fitment| {
+        (0..dataset.axis_days())
+            .map(|x| (x as f64, fitment.eval(x as f64)))
+            .collect()
+    });
+    let fit = curves.next().unwrap_or_default();
+    let alternatives = curves.collect();
+
+    Series {
+        location: dataset.label.clone(),
+        quantity: quantity.label(),
+        color: format!("rgb({},{},{})", color.0, color.1, color.2),
+        dash: multiple
+            .then(|| quantity_dash(quantity))
+            .flatten()
+            .map(|(size, spacing)| format!("{} {}", size, spacing)),
+        points,
+        fit,
+        alternatives,
+        band: dataset.prediction_band(quantity),
+    }
+}
+
+/// Write a self-contained interactive chart (inline script, no external
+/// resources) with hover tooltips and per-series toggles.
+pub fn write_html_chart(output: &Path, caption: &str, datasets: &[Dataset]) {
+    let multiple = datasets.len() > 1;
+    let page = Page {
+        caption,
+        day_axis: match datasets {
+            [dataset] => dataset.day_axis(),
+            _ => "Day".to_string(),
+        },
+        days: datasets.iter().map(Dataset::axis_days).max().unwrap_or(365),
+        series: datasets
+            .iter()
+            .enumerate()
+            .flat_map(|(index, dataset)| {
+                dataset
+                    .quantities_in(Unit::Hours)
+                    .into_iter()
+                    .map(move |quantity| build_series(index, multiple, dataset, quantity))
+            })
+            .collect(),
+    };
+
+    // keep the embedded JSON from closing the <script> element early
+    let data = serde_json::to_string(&page)
+        .expect("Unable to serialize JSON")
+        .replace("</", "<\\/");
+    let title = caption
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;");
+
+    let html = TEMPLATE
+        .replace("__TITLE__", &title)
+        .replace("__DATA__", &data);
+
+    fs::write(output, html).expect("Unable to write file");
+}
@@ -0,0 +1,19 @@
+//! Sunrise/sunset tooling shared by the `sun` fetcher and `json2day`: the
+//! timeanddate.com client, the per-day record model, the sine fitter, the
+//! input validator, the year analytics, the equation of time and the chart
+//! renderers (SVG, interactive HTML and terminal braille).
+
+pub mod analytics;
+pub mod cache;
+pub mod client;
+pub mod day;
+pub mod eot;
+pub mod fit;
+pub mod format;
+pub mod html;
+pub mod manifest;
+pub mod place;
+pub mod plot;
+pub mod solar;
+pub mod term;
+pub mod validate;
@@ -1,26 +1,27 @@
 use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
 
 use clap::Parser;
 use dotenv::dotenv;
 
-use libtad_rs::models::astronomy;
+use libtad_rs::models::astronomy::{self, AstronomyDay, AstronomyDayEvent, MoonPhase};
+use libtad_rs::models::places::Geo;
 use libtad_rs::models::time;
-use libtad_rs::ServiceClient;
 
+use libtad_rs::service::astronomy::{AstroEventRequest, AstronomyResponse};
+use libtad_rs::service::time::TimeserviceRequest;
 use serde::Serialize;
+use tokio::sync::Semaphore;
+
+use sun::cache::Cache;
+use sun::client::{Client, FetchError, RetryPolicy};
+use sun::day::{day_of_year, Day, DayFile, NumOrStr};
+use sun::manifest::{self, ManifestEntry};
+use sun::place::{self, AstroInfo, PlaceQuery};
+use sun::solar;
 //use serde_json::json;
 //
-#[derive(Serialize)]
-struct AstroInfo {
-    country: String,
-    state: String,
-    city: String,
-    lat: f32,
-    lon: f32,
-    lat_dir: String,
-    lon_dir: String,
-}
-
 //#[derive(Serialize)]
 //struct AstroDay {
 //    date: time::DateTime,
@@ -30,75 +31,606 @@ struct AstroInfo {
 //    antimeridian: String,
 //}
 
+/// Celestial bodies the Astro Event API reports on.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+enum Body {
+    Sun,
+    Moon,
+    Mercury,
+    Venus,
+    Mars,
+    Jupiter,
+    Saturn,
+    Uranus,
+    Neptune,
+    Pluto,
+}
+
+impl Body {
+    fn object_type(self) -> astronomy::AstronomyObjectType {
+        use astronomy::AstronomyObjectType as T;
+        match self {
+            Body::Sun => T::Sun,
+            Body::Moon => T::Moon,
+            Body::Mercury => T::Mercury,
+            Body::Venus => T::Venus,
+            Body::Mars => T::Mars,
+            Body::Jupiter => T::Jupiter,
+            Body::Saturn => T::Saturn,
+            Body::Uranus => T::Uranus,
+            Body::Neptune => T::Neptune,
+            Body::Pluto => T::Pluto,
+        }
+    }
+}
+
 #[derive(Parser, Debug)]
 #[command(author, version, about, long_about = None)]
 struct Args {
-    /// API key
+    /// API key (not needed when the response is cached)
     #[arg(short, long, env = "API_KEY", value_name = "API-KEY")]
-    api: String,
+    api: Option<String>,
 
     /// Secret key
     #[arg(short, long, env = "SECRET_KEY", value_name = "SECRET-KEY")]
-    secret: String,
+    secret: Option<String>,
 
-    /// Location
-    #[arg(short, long, env = "LOCATION", value_name = "LOCATION")]
-    location: String,
+    /// Location: a place id ("usa/seattle", "179") or a name ("Seattle, WA")
+    #[arg(
+        short,
+        long,
+        env = "LOCATION",
+        value_name = "LOCATION",
+        required_unless_present_any = ["manifest", "coords"]
+    )]
+    location: Option<String>,
+
+    /// Fetch for these coordinates instead of --location
+    #[arg(long, value_name = "LAT,LON", allow_hyphen_values = true, value_parser = place::parse_coords, conflicts_with = "manifest")]
+    coords: Option<(f64, f64)>,
+
+    /// First day to fetch (YYYY-MM-DD)
+    #[arg(long, value_parser = manifest::parse_date, default_value = "2024-01-01")]
+    start: time::DateTime,
+
+    /// Day to fetch up to (YYYY-MM-DD)
+    #[arg(long, value_parser = manifest::parse_date, default_value = "2024-02-01")]
+    end: time::DateTime,
+
+    /// TOML or CSV list of locations to fetch instead of --location
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["location", "output", "dump"])]
+    manifest: Option<PathBuf>,
+
+    /// Where batch datasets and their index.json go
+    #[arg(long, value_name = "DIR", default_value = ".")]
+    output_dir: PathBuf,
+
+    /// Locations fetched at once in batch mode
+    #[arg(long, default_value_t = 4)]
+    jobs: usize,
 
     /// Dump filename
     #[arg(short, long, env = "DUMP", value_name = "DUMP")]
     dump: Option<std::path::PathBuf>,
+
+    /// Output dataset JSON file (json2day input); sun and moon only
+    #[arg(short, long, env = "OUTPUT", value_name = "OUTPUT")]
+    output: Option<std::path::PathBuf>,
+
+    /// Retries after network errors, rate limiting and server errors
+    #[arg(long, env = "RETRIES", default_value_t = 3)]
+    retries: u32,
+
+    /// Requests per second to the API, shared by all --jobs; 0 for no limit
+    #[arg(long, env = "RATE", default_value_t = sun::client::DEFAULT_RATE)]
+    rate: f64,
+
+    /// API base URL
+    #[arg(long, env = "BASE_URL", default_value = sun::client::BASE_URL)]
+    base_url: String,
+
+    /// Response cache directory (defaults to $XDG_CACHE_HOME/sun)
+    #[arg(long, env = "CACHE_DIR", value_name = "DIR")]
+    cache_dir: Option<std::path::PathBuf>,
+
+    /// Hours before a cached response is fetched again
+    #[arg(long, env = "CACHE_TTL", value_name = "HOURS", default_value_t = 720)]
+    cache_ttl: u64,
+
+    /// Neither read nor write the response cache
+    #[arg(long, conflicts_with = "offline")]
+    no_cache: bool,
+
+    /// Only answer from the cache, never query the API
+    #[arg(long)]
+    offline: bool,
+
+    /// Body to fetch rise/set/transit events for
+    #[arg(long, value_enum, default_value_t = Body::Sun)]
+    object: Body,
 }
 
-fn main() {
-    dotenv().ok();
+fn event<'a>(day: &'a AstronomyDay, kind: &str) -> Option<&'a AstronomyDayEvent> {
+    day.events.iter().find(|event| event.r#type == kind)
+}
 
-    let args = Args::parse();
+fn event_hours(day: &AstronomyDay, kind: &str) -> Option<NumOrStr> {
+    event(day, kind).map(|event| {
+        NumOrStr::Num(event.hour as f64 + event.min as f64 / 60.0 + event.sec as f64 / 3600.0)
+    })
+}
+
+fn hms(day: &AstronomyDay, kind: &str) -> String {
+    event(day, kind)
+        .map(|event| format!("{}:{}:{}", event.hour, event.min, event.sec))
+        .unwrap_or_else(|| "-".to_string())
+}
+
+fn phase_name(phase: &MoonPhase) -> &'static str {
+    match phase {
+        MoonPhase::NewMoon => "newmoon",
+        MoonPhase::WaxingCrescent => "waxingcrescent",
+        MoonPhase::FirstQuarter => "firstquarter",
+        MoonPhase::WaxingGibbous => "waxinggibbous",
+        MoonPhase::FullMoon => "fullmoon",
+        MoonPhase::WaningGibbous => "waninggibbous",
+        MoonPhase::ThirdQuarter => "thirdquarter",
+        MoonPhase::WaningCrescent => "waningcrescent",
+    }
+}
+
+fn record_date(day: &AstronomyDay) -> Day {
+    let (y, m, d) = (
+        day.date.year as u64,
+        day.date.month as u64,
+        day.date.day as u64,
+    );
+    Day {
+        y,
+        m,
+        d,
+        yday: day_of_year(y, m, d),
+        ..Default::default()
+    }
+}
 
-    let api_key = args.api;
-    let secret_key = args.secret;
+fn sun_day(day: &AstronomyDay, geo: &Geo) -> Day {
+    println!(
+        "date: {}, sunrise: {}, sunset: {}, civil: {}-{}, nautical: {}-{}, astronomical: {}-{}",
+        day.date,
+        hms(day, "rise"),
+        hms(day, "set"),
+        hms(day, "civil_start"),
+        hms(day, "civil_end"),
+        hms(day, "nautical_start"),
+        hms(day, "nautical_end"),
+        hms(day, "astronomical_start"),
+        hms(day, "astronomical_end"),
+    );
 
-    let client = ServiceClient::new(api_key, secret_key);
+    let angle = |kind: &str, value: fn(&AstronomyDayEvent) -> Option<f32>| {
+        event(day, kind)
+            .and_then(value)
+            .map(|angle| NumOrStr::Num(angle as f64))
+    };
+
+    let mut record = Day {
+        srise: event_hours(day, "rise"),
+        sset: event_hours(day, "set"),
+        solnoon: event_hours(day, "meridian"),
+        civdawn: event_hours(day, "civil_start"),
+        civdusk: event_hours(day, "civil_end"),
+        nautdawn: event_hours(day, "nautical_start"),
+        nautdusk: event_hours(day, "nautical_end"),
+        astrodawn: event_hours(day, "astronomical_start"),
+        astrodusk: event_hours(day, "astronomical_end"),
+        sriseaz: angle("rise", |event| event.azimuth),
+        ssetaz: angle("set", |event| event.azimuth),
+        noonalt: angle("meridian", |event| event.altitude),
+        ..record_date(day)
+    };
+
+    // fill in whatever angles the API left out
+    if let (Some(lat), Some(lon)) = (geo.latitude, geo.longitude) {
+        let (lat, lon) = (lat as f64, lon as f64);
+        let sun = solar::solar_day(record.y, record.m, record.d, lat, lon, lon / 15.0);
+        record.sriseaz = record.sriseaz.or(sun.rise_azimuth.map(NumOrStr::Num));
+        record.ssetaz = record.ssetaz.or(sun.set_azimuth.map(NumOrStr::Num));
+        record.noonalt = record.noonalt.or(Some(NumOrStr::Num(sun.noon_altitude)));
+    }
+    record.normalize();
+    record
+}
 
-    let mut astro_info: Option<AstroInfo> = None;
+/// The moon rises ~50 minutes later each day, so roughly once a month a
+/// date has no moonrise (or moonset); those fields are left out.
+fn moon_day(day: &AstronomyDay) -> Day {
+    let phase = day.moonphase.as_ref().map(phase_name);
+    // illumination at transit, or at whichever event reports it
+    let illuminated = event(day, "meridian")
+        .and_then(|event| event.illuminated)
+        .or_else(|| day.events.iter().find_map(|event| event.illuminated));
 
-    let req = libtad_rs::service::astronomy::AstroEventRequest::new()
-        .with_object(astronomy::AstronomyObjectType::Sun)
-        .with_placeid(args.location)
+    println!(
+        "date: {}, moonrise: {}, moonset: {}, transit: {}, phase: {}, illuminated: {}",
+        day.date,
+        hms(day, "rise"),
+        hms(day, "set"),
+        hms(day, "meridian"),
+        phase.unwrap_or("-"),
+        illuminated
+            .map(|illuminated| format!("{:.1}%", illuminated))
+            .unwrap_or_else(|| "-".to_string()),
+    );
+
+    let mut record = Day {
+        mrise: event_hours(day, "rise"),
+        mset: event_hours(day, "set"),
+        mtransit: event_hours(day, "meridian"),
+        mphase: phase.map(str::to_string),
+        millum: illuminated.map(|illuminated| NumOrStr::Num(illuminated as f64)),
+        ..record_date(day)
+    };
+    record.normalize();
+    record
+}
+
+fn print_body_day(day: &AstronomyDay) {
+    println!(
+        "date: {}, rise: {}, set: {}, transit: {}",
+        day.date,
+        hms(day, "rise"),
+        hms(day, "set"),
+        hms(day, "meridian"),
+    );
+}
+
+fn astro_request(
+    object: Body,
+    location: String,
+    start: time::DateTime,
+    end: time::DateTime,
+) -> AstroEventRequest {
+    let req = AstroEventRequest::new()
+        .with_object(object.object_type())
+        .with_placeid(location)
         //.set_startdt(libtad_rs::models::time::DateTime::from(
         //    "2024-10-17T00:00:00",
         //))
         //.set_enddt(libtad_rs::models::time::DateTime::from(
         //    "2024-10-19T23:59:59",
         //));
-        .set_startdt(time::DateTime {
-            year: 2024,
-            month: 1,
-            day: 1,
-            hour: 0,
-            minute: 0,
-            second: 0,
-        })
-        .set_enddt(time::DateTime {
-            year: 2024,
-            month: 2,
-            day: 1,
-            hour: 0,
-            minute: 0,
-            second: 0,
-        })
+        .set_startdt(start)
+        .set_enddt(end)
         .with_type(astronomy::AstronomyEventClass::Meridian)
         .with_type(astronomy::AstronomyEventClass::SetRise)
+        .set_lang("en")
+        .set_utctime(true);
+
+    // twilight only exists for the sun, phases only for the moon
+    match object {
+        Body::Sun => req.with_type(astronomy::AstronomyEventClass::Twilight),
+        Body::Moon => req.with_type(astronomy::AstronomyEventClass::Phase),
+        _ => req,
+    }
+}
+
+/// The place id to fetch for a location. Names and coordinates are looked up
+/// with the places endpoint first, giving the location's details; place ids
+/// are passed through as they are.
+async fn resolve(
+    client: &Client,
+    query: &PlaceQuery,
+) -> Result<(String, Option<AstroInfo>), FetchError> {
+    if let PlaceQuery::Id(id) = query {
+        return Ok((id.clone(), None));
+    }
+
+    let places = client.get_places(&query.places_request()).await?.places;
+    let Some(place) = places.first() else {
+        return Err(FetchError::PlaceNotFound(format!(
+            "no matches for {}",
+            query
+        )));
+    };
+    if let PlaceQuery::Name(_) = query {
+        for other in &places[1..] {
+            eprintln!(
+                "also matched: {} (--location {})",
+                AstroInfo::from(&other.geo),
+                other.id
+            );
+        }
+    }
+
+    // coordinates are fetched exactly, not for the nearest city
+    let info = AstroInfo::from(&place.geo);
+    Ok(match query {
+        PlaceQuery::Coords(lat, lon) => {
+            (PlaceQuery::coords_id(*lat, *lon), Some(info.at(*lat, *lon)))
+        }
+        _ => (place.id.to_string(), Some(info)),
+    })
+}
+
+/// Name and standard UTC offset (hours) of a place's time zone, from the
+/// time service; `None`, with a warning, if it can't tell.
+async fn time_zone(client: &Client, placeid: &str) -> Option<(String, f64)> {
+    let req = TimeserviceRequest::new()
+        .set_placeid(placeid)
+        .set_tz(true)
         .set_lang("en");
+    let res = match client.get_current_time(&req).await {
+        Ok(res) => res,
+        Err(e) => {
+            eprintln!("No time zone for {}: {}", placeid, e);
+            return None;
+        }
+    };
+    let timezone = res.locations.first()?.time.as_ref()?.timezone.as_ref()?;
+    let name = timezone
+        .zonename
+        .clone()
+        .unwrap_or_else(|| timezone.zoneabb.clone());
+    Some((name, timezone.zoneoffset as f64 / 3600.0))
+}
+
+/// Hours ahead of UTC on a day, from an event's local and UTC times.
+fn utc_offset(day: &AstronomyDay) -> Option<f64> {
+    let naive =
+        |date: &time::DateTime, (hour, min, sec): (i32, i32, i32)| {
+            chrono::NaiveDate::from_ymd_opt(date.year, date.month as u32, date.day as u32)?
+                .and_hms_opt(hour as u32, min as u32, sec as u32)
+        };
 
-    let res = client.get_astro_events(&req);
+    let event = day.events.iter().find(|event| event.utctime.is_some())?;
+    let utc = event.utctime?;
+    let local = naive(&day.date, (event.hour, event.min, event.sec))?;
+    let utc = naive(&utc, (utc.hour, utc.minute, utc.second))?;
+    Some((local - utc).num_minutes() as f64 / 60.0)
+}
+
+/// Print the days of a response and turn them into dataset records; the
+/// first location's details go to `astro_info`, with the time zone if
+/// known. Without it the standard UTC offset is taken to be the least one
+/// over the days, which is right unless they all fall on daylight saving.
+fn collect_records(
+    astro_events: &AstronomyResponse,
+    object: Body,
+    astro_info: &mut Option<AstroInfo>,
+    zone: Option<(String, f64)>,
+) -> Vec<Day> {
+    let mut records: Vec<Day> = Vec::new();
+    let mut offsets: Vec<f64> = Vec::new();
+
+    for location in astro_events.locations.iter() {
+        if astro_info.is_none() {
+            *astro_info = Some(AstroInfo::from(&location.geo));
+        }
+
+        for astro in location.astronomy.objects.iter() {
+            // println!("astro: {:?}", astro);
+            if let Some(current) = astro.current.as_ref() {
+                println!("current: {:?}", current);
+            }
+
+            if let Some(days) = astro.days.as_ref() {
+                println!("days: {}", days.len());
+
+                for day in days.iter() {
+                    println!("day: {:?}", day);
+                    offsets.extend(utc_offset(day));
+                    let record = match object {
+                        Body::Sun => sun_day(day, &location.geo),
+                        Body::Moon => moon_day(day),
+                        _ => {
+                            print_body_day(day);
+                            continue;
+                        }
+                    };
+                    records.push(record);
+                }
+            }
+        }
+    }
+
+    if let Some(info) = astro_info.as_mut() {
+        match zone {
+            Some((name, offset)) => {
+                info.timezone = Some(name);
+                info.utc_offset = Some(offset);
+            }
+            None => info.utc_offset = offsets.into_iter().reduce(f64::min),
+        }
+    }
+
+    records
+}
+
+/// Write a dataset, under a location header when the location is known.
+fn write_records(output: &Path, location: Option<AstroInfo>, records: Vec<Day>) {
+    let output_json = serde_json::to_string_pretty(&DayFile::new(location, records))
+        .expect("Unable to serialize JSON");
+    std::fs::write(output, output_json).expect("Unable to write file");
+}
+
+/// A line of the batch summary index.
+#[derive(Serialize)]
+struct IndexEntry {
+    label: String,
+    location: String,
+    start: String,
+    end: String,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    file: Option<String>,
+    days: usize,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+/// Fetch every manifest entry, at most `jobs` at a time, write one dataset
+/// per location and an `index.json` summary to `output_dir`. Returns the
+/// exit code of the first failure, or 0.
+async fn fetch_batch(
+    client: Arc<Client>,
+    entries: Vec<ManifestEntry>,
+    object: Body,
+    (start, end): (time::DateTime, time::DateTime),
+    output_dir: &Path,
+    jobs: usize,
+) -> i32 {
+    std::fs::create_dir_all(output_dir).expect("Unable to create output directory");
+
+    let permits = Arc::new(Semaphore::new(jobs.max(1)));
+    let tasks: Vec<_> = entries
+        .iter()
+        .map(|entry| {
+            let client = Arc::clone(&client);
+            let permits = Arc::clone(&permits);
+            let query = PlaceQuery::parse(&entry.location);
+            let range = (entry.start.unwrap_or(start), entry.end.unwrap_or(end));
+            tokio::spawn(async move {
+                let _permit = permits.acquire_owned().await.expect("semaphore closed");
+                let (placeid, info) = resolve(&client, &query).await?;
+                let zone = time_zone(&client, &placeid).await;
+                let req = astro_request(object, placeid, range.0, range.1);
+                client
+                    .get_astro_events(&req)
+                    .await
+                    .map(|events| (events, info, zone))
+            })
+        })
+        .collect();
+
+    // results are handled in manifest order, so the output doesn't interleave
+    let mut index = Vec::new();
+    let mut code = 0;
+    for (entry, task) in entries.iter().zip(tasks) {
+        println!("== {} ==", entry.label);
+        let mut line = IndexEntry {
+            label: entry.label.clone(),
+            location: entry.location.clone(),
+            start: manifest::format_date(&entry.start.unwrap_or(start)),
+            end: manifest::format_date(&entry.end.unwrap_or(end)),
+            file: None,
+            days: 0,
+            error: None,
+        };
+
+        match task.await.expect("fetch task panicked") {
+            Ok((astro_events, mut info, zone)) => {
+                if let Some(info) = &info {
+                    println!("location: {}", info);
+                }
+                let records = collect_records(&astro_events, object, &mut info, zone);
+                let file = entry.output.clone();
+                line.days = records.len();
+                write_records(&output_dir.join(&file), info, records);
+                line.file = Some(file);
+            }
+            Err(e) => {
+                eprintln!("{}: {}", entry.label, e);
+                line.error = Some(e.to_string());
+                if code == 0 {
+                    code = e.exit_code();
+                }
+            }
+        }
+        index.push(line);
+    }
+
+    let failed = index.iter().filter(|line| line.error.is_some()).count();
+    let index_json = serde_json::to_string_pretty(&index).expect("Unable to serialize JSON");
+    std::fs::write(output_dir.join(manifest::INDEX), index_json).expect("Unable to write file");
+    println!(
+        "Fetched {} of {} locations into {}",
+        index.len() - failed,
+        index.len(),
+        output_dir.display()
+    );
+
+    code
+}
+
+#[tokio::main]
+async fn main() {
+    dotenv().ok();
+
+    let args = Args::parse();
+
+    if (args.output.is_some() || args.manifest.is_some())
+        && !matches!(args.object, Body::Sun | Body::Moon)
+    {
+        eprintln!("--output and --manifest only support --object sun or moon");
+        std::process::exit(2);
+    }
+
+    let api_key = args.api.unwrap_or_default();
+    let secret_key = args.secret.unwrap_or_default();
+
+    let mut client = Client::new(api_key, secret_key)
+        .with_base_url(args.base_url)
+        .with_retry(RetryPolicy {
+            retries: args.retries,
+            ..RetryPolicy::default()
+        })
+        .with_rate(args.rate)
+        .with_offline(args.offline);
+
+    if !args.no_cache {
+        match args.cache_dir.or_else(Cache::default_dir) {
+            Some(dir) => {
+                let ttl = std::time::Duration::from_secs(args.cache_ttl * 3600);
+                client = client.with_cache(Cache::new(dir, ttl));
+            }
+            None => eprintln!("No cache directory (set --cache-dir or $XDG_CACHE_HOME)"),
+        }
+    }
+
+    if let Some(manifest) = args.manifest {
+        let entries = manifest::load(&manifest).unwrap_or_else(|e| {
+            eprintln!("Unable to read manifest {}", e);
+            std::process::exit(2);
+        });
+        let code = fetch_batch(
+            Arc::new(client),
+            entries,
+            args.object,
+            (args.start, args.end),
+            &args.output_dir,
+            args.jobs,
+        )
+        .await;
+        std::process::exit(code);
+    }
+
+    let query = match args.coords {
+        Some((lat, lon)) => PlaceQuery::Coords(lat, lon),
+        None => PlaceQuery::parse(
+            &args
+                .location
+                .expect("--location is required without --manifest or --coords"),
+        ),
+    };
+    let (placeid, mut astro_info) = resolve(&client, &query).await.unwrap_or_else(|e| {
+        eprintln!("{}", e);
+        std::process::exit(e.exit_code());
+    });
+    if let Some(info) = &astro_info {
+        println!("location: {}", info);
+    }
+
+    let zone = time_zone(&client, &placeid).await;
+    let req = astro_request(args.object, placeid, args.start, args.end);
+
+    let res = client.get_astro_events(&req).await;
 
     if let Some(dump_path) = args.dump {
         match &res {
             Ok(astro_events) => {
                 let mut dump_file = std::fs::File::create(dump_path).unwrap();
                 let fmted = format!("{:?}", astro_events);
-                dump_file.write(fmted.as_bytes()).unwrap();
+                dump_file.write_all(fmted.as_bytes()).unwrap();
             }
             Err(e) => {
                 println!("{:?}", e);
@@ -106,124 +638,17 @@ fn main() {
         }
     }
 
-    match &res {
-        Ok(astro_events) => {
-            astro_events.into_iter().for_each(|event| {
-                for location in event.locations.iter() {
-                    if astro_info.is_none() {
-                        let country = &location.geo.country.name;
-                        let state = location.geo.state.as_deref().unwrap_or_default();
-                        let city = &location.geo.name;
-                        let lat_lon: [f32; 2] = [
-                            location.geo.latitude.unwrap_or_default(),
-                            location.geo.longitude.unwrap_or_default(),
-                        ];
-
-                        //println!(
-                        //    "location: {}, {}, {}, {}{} {}{}",
-                        //    country,
-                        //    state,
-                        //    city,
-                        //    lat_lon[0],
-                        //    if lat_lon[0] > 0.0 { "N" } else { "S" },
-                        //    lat_lon[1],
-                        //    if lat_lon[1] > 0.0 { "E" } else { "W" }
-                        //);
-
-                        astro_info = Some(AstroInfo {
-                            country: country.clone(),
-                            state: state.to_owned(),
-                            city: city.clone(),
-                            lat: lat_lon[0],
-                            lon: lat_lon[1],
-                            lat_dir: if lat_lon[0] > 0.0 { "N" } else { "S" }.to_string(),
-                            lon_dir: if lat_lon[1] > 0.0 { "E" } else { "W" }.to_string(),
-                        });
-                    }
-
-                    for astro in location.astronomy.objects.iter() {
-                        // println!("astro: {:?}", astro);
-                        if let Some(current) = astro.current.as_ref() {
-                            println!("current: {:?}", current);
-                        }
-
-                        if let Some(days) = astro.days.as_ref() {
-                            println!("days: {}", days.len());
-
-                            for day in days.iter() {
-                                println!("day: {:?}", day);
-                                let date = day.date.to_string();
-
-                                let sunrise = day
-                                    .events
-                                    .iter()
-                                    .find(|event| {
-                                        let event_type = &event.r#type;
-                                        if event_type == "rise" {
-                                            true
-                                        } else {
-                                            false
-                                        }
-                                    })
-                                    .unwrap();
-
-                                let sunset = day
-                                    .events
-                                    .iter()
-                                    .find(|event| {
-                                        let event_type = &event.r#type;
-                                        if event_type == "set" {
-                                            true
-                                        } else {
-                                            false
-                                        }
-                                    })
-                                    .unwrap();
-
-                                //let meridian = day
-                                //    .events
-                                //    .iter()
-                                //    .find(|event| {
-                                //        let event_type = &event.r#type;
-                                //        if event_type == "meridian" {
-                                //            true
-                                //        } else {
-                                //            false
-                                //        }
-                                //    })
-                                //    .unwrap();
-
-                                // let antimeridian = day
-                                //     .events
-                                //     .iter()
-                                //     .find(|event| {
-                                //         let event_type = &event.r#type;
-                                //         if event_type == "antimeridian" {
-                                //             true
-                                //         } else {
-                                //             false
-                                //         }
-                                //     })
-                                //     .unwrap();
-
-                                println!(
-                                    "date: {}, sunrise: {}:{}:{}, sunset: {}:{}:{}",
-                                    date,
-                                    sunrise.hour,
-                                    sunrise.min,
-                                    sunrise.sec,
-                                    sunset.hour,
-                                    sunset.min,
-                                    sunset.sec
-                                );
-                            }
-                        }
-                    }
-                }
-            });
-        }
+    let astro_events = match res {
+        Ok(astro_events) => astro_events,
         Err(e) => {
-            println!("{:?}", e);
+            eprintln!("{}", e);
+            std::process::exit(e.exit_code());
         }
+    };
+
+    let records = collect_records(&astro_events, args.object, &mut astro_info, zone);
+
+    if let Some(output) = args.output {
+        write_records(&output, astro_info, records);
     }
 }
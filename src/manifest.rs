@@ -0,0 +1,170 @@
+//! Lists of locations to fetch in one go, as TOML or CSV.
+//!
+//! TOML:
+//!
+//! ```toml
+//! [[location]]
+//! label = "Seattle, WA"
+//! location = "usa/seattle"
+//! start = "2024-01-01"
+//! end = "2024-12-31"
+//! ```
+//!
+//! CSV, with a header row: `label,location,start,end,output`. Only `label`
+//! and `location` are required; `output` names the dataset file, a slug of
+//! the label by default. It must be a plain file name, inside the output
+//! directory, and two entries may not write the same file.
+
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+
+use libtad_rs::models::time::DateTime;
+use serde::Deserialize;
+
+/// One location to fetch.
+#[derive(Debug, Clone)]
+pub struct ManifestEntry {
+    pub label: String,
+    /// Place id ("usa/seattle", "179") or a place name to look up
+    pub location: String,
+    pub start: Option<DateTime>,
+    pub end: Option<DateTime>,
+    /// Dataset file name, relative to the output directory
+    pub output: String,
+}
+
+/// An entry as written in the file, dates still unparsed.
+#[derive(Deserialize)]
+struct RawEntry {
+    label: String,
+    location: String,
+    #[serde(default)]
+    start: Option<String>,
+    #[serde(default)]
+    end: Option<String>,
+    #[serde(default)]
+    output: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct TomlManifest {
+    location: Vec<RawEntry>,
+}
+
+/// Midnight of a `YYYY-MM-DD` date.
+pub fn parse_date(value: &str) -> Result<DateTime, String> {
+    let parts: Vec<&str> = value.trim().split('-').collect();
+    let [year, month, day] = parts[..] else {
+        return Err(format!("expected YYYY-MM-DD, got {:?}", value));
+    };
+    let number = |part: &str| {
+        part.parse::<i32>()
+            .map_err(|_| format!("expected YYYY-MM-DD, got {:?}", value))
+    };
+    let (year, month, day) = (number(year)?, number(month)?, number(day)?);
+    if !(1..=12).contains(&month) || !(1..=31).contains(&day) {
+        return Err(format!("{:?} is not a valid date", value));
+    }
+    Ok(DateTime {
+        year,
+        month,
+        day,
+        ..Default::default()
+    })
+}
+
+/// `YYYY-MM-DD` for a date.
+pub fn format_date(date: &DateTime) -> String {
+    format!("{:04}-{:02}-{:02}", date.year, date.month, date.day)
+}
+
+/// The summary written next to the datasets, which no entry may replace.
+pub const INDEX: &str = "index.json";
+
+/// Read a manifest; `.csv` files are read as CSV, everything else as TOML.
+pub fn load(path: &Path) -> Result<Vec<ManifestEntry>, String> {
+    let input = fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse(path, &input)
+}
+
+/// A manifest's entries from its text, read as CSV if `path` ends in
+/// `.csv` and as TOML otherwise; `path` also prefixes the errors.
+pub fn parse(path: &Path, input: &str) -> Result<Vec<ManifestEntry>, String> {
+    let raw: Vec<RawEntry> = if path.extension().is_some_and(|ext| ext == "csv") {
+        csv::ReaderBuilder::new()
+            .trim(csv::Trim::All)
+            .from_reader(input.as_bytes())
+            .deserialize()
+            .collect::<Result<_, _>>()
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+    } else {
+        toml::from_str::<TomlManifest>(input)
+            .map_err(|e| format!("{}: {}", path.display(), e))?
+            .location
+    };
+
+    let optional_date = |value: Option<String>| {
+        value
+            .filter(|value| !value.is_empty())
+            .map(|value| parse_date(&value))
+            .transpose()
+    };
+
+    let entries =
+        raw.into_iter()
+            .map(|entry| {
+                let context = |e: String| format!("{} ({}): {}", path.display(), entry.label, e);
+                let output = match entry.output.filter(|output| !output.is_empty()) {
+                    Some(output) if Path::new(&output).file_name() != Some(output.as_ref()) => {
+                        return Err(context(format!(
+                            "output {:?} is not a plain file name in the output directory",
+                            output
+                        )))
+                    }
+                    Some(output) => output,
+                    None => match slug(&entry.label) {
+                        slug if slug.is_empty() => return Err(context(String::from(
+                            "no letters or digits in the label to name the file; give an output",
+                        ))),
+                        slug => format!("{}.json", slug),
+                    },
+                };
+                Ok(ManifestEntry {
+                    start: optional_date(entry.start.clone()).map_err(context)?,
+                    end: optional_date(entry.end.clone()).map_err(context)?,
+                    output,
+                    label: entry.label,
+                    location: entry.location,
+                })
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+    // file systems may ignore case
+    let mut outputs: HashMap<String, &str> = HashMap::from([(INDEX.to_string(), "the index")]);
+    for entry in &entries {
+        if let Some(other) = outputs.insert(entry.output.to_lowercase(), &entry.label) {
+            return Err(format!(
+                "{}: {} and {} would both be written to {}",
+                path.display(),
+                other,
+                entry.label,
+                entry.output
+            ));
+        }
+    }
+    Ok(entries)
+}
+
+/// A file-name-safe version of a label: "Seattle, WA" becomes "seattle-wa".
+pub fn slug(label: &str) -> String {
+    let mut slug = String::new();
+    for c in label.chars() {
+        if c.is_ascii_alphanumeric() {
+            slug.push(c.to_ascii_lowercase());
+        } else if !slug.is_empty() && !slug.ends_with('-') {
+            slug.push('-');
+        }
+    }
+    slug.trim_end_matches('-').to_string()
+}
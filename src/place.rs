@@ -0,0 +1,193 @@
+//! Where a dataset was fetched for: resolving `--location`/`--coords` into a
+//! place the API knows, and the location header printed before fetching and
+//! stored with the dataset.
+
+use std::fmt;
+
+use libtad_rs::models::places::Geo;
+use libtad_rs::service::places::PlacesRequest;
+use serde::{Deserialize, Serialize};
+
+/// Matches asked for when looking a place up by name.
+const QUERY_LIMIT: u8 = 5;
+
+/// Country, state, city and coordinates of a location.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AstroInfo {
+    pub country: String,
+    pub state: String,
+    pub city: String,
+    pub lat: f32,
+    pub lon: f32,
+    pub lat_dir: String,
+    pub lon_dir: String,
+    /// Hours ahead of UTC on standard time, without daylight saving
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub utc_offset: Option<f64>,
+    /// Time zone name, e.g. "Pacific Standard Time", as the API reported it
+    /// when fetching
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub timezone: Option<String>,
+}
+
+impl From<&Geo> for AstroInfo {
+    fn from(geo: &Geo) -> Self {
+        let lat_lon: [f32; 2] = [
+            geo.latitude.unwrap_or_default(),
+            geo.longitude.unwrap_or_default(),
+        ];
+
+        //println!(
+        //    "location: {}, {}, {}, {}{} {}{}",
+        //    country,
+        //    state,
+        //    city,
+        //    lat_lon[0],
+        //    if lat_lon[0] > 0.0 { "N" } else { "S" },
+        //    lat_lon[1],
+        //    if lat_lon[1] > 0.0 { "E" } else { "W" }
+        //);
+
+        AstroInfo {
+            country: geo.country.name.clone(),
+            state: geo.state.clone().unwrap_or_default(),
+            city: geo.name.clone(),
+            lat: lat_lon[0],
+            lon: lat_lon[1],
+            lat_dir: if lat_lon[0] > 0.0 { "N" } else { "S" }.to_string(),
+            lon_dir: if lat_lon[1] > 0.0 { "E" } else { "W" }.to_string(),
+            utc_offset: None,
+            timezone: None,
+        }
+    }
+}
+
+impl AstroInfo {
+    /// The same place at other coordinates.
+    pub fn at(self, lat: f64, lon: f64) -> Self {
+        AstroInfo {
+            lat: lat as f32,
+            lon: lon as f32,
+            lat_dir: if lat > 0.0 { "N" } else { "S" }.to_string(),
+            lon_dir: if lon > 0.0 { "E" } else { "W" }.to_string(),
+            ..self
+        }
+    }
+
+    /// "Seattle, Washington"
+    pub fn short_name(&self) -> String {
+        if self.state.is_empty() {
+            self.city.clone()
+        } else {
+            format!("{}, {}", self.city, self.state)
+        }
+    }
+
+    /// "Seattle, Washington, United States"
+    pub fn full_name(&self) -> String {
+        format!("{}, {}", self.short_name(), self.country)
+    }
+
+    /// "47.61°N 122.33°W, UTC-08:00"
+    pub fn position(&self) -> String {
+        let mut position = format!(
+            "{:.2}°{} {:.2}°{}",
+            self.lat.abs(),
+            self.lat_dir,
+            self.lon.abs(),
+            self.lon_dir
+        );
+        if let Some(offset) = self.utc_offset {
+            let minutes = (offset * 60.0).round() as i64;
+            position += &format!(
+                ", UTC{}{:02}:{:02}",
+                if minutes < 0 { '-' } else { '+' },
+                minutes.abs() / 60,
+                minutes.abs() % 60
+            );
+        }
+        position
+    }
+}
+
+/// "Seattle, Washington, United States (47.61°N 122.33°W, UTC-08:00,
+/// Pacific Standard Time)"
+impl fmt::Display for AstroInfo {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        write!(f, "{} ({}", self.full_name(), self.position())?;
+        if let Some(timezone) = &self.timezone {
+            write!(f, ", {}", timezone)?;
+        }
+        write!(f, ")")
+    }
+}
+
+/// A `LAT,LON` pair in decimal degrees, north and east positive.
+pub fn parse_coords(value: &str) -> Result<(f64, f64), String> {
+    let (lat, lon) = value
+        .split_once(',')
+        .ok_or_else(|| format!("expected LAT,LON, got {:?}", value))?;
+    let lat: f64 = lat.trim().parse().map_err(|e| format!("latitude: {}", e))?;
+    let lon: f64 = lon
+        .trim()
+        .parse()
+        .map_err(|e| format!("longitude: {}", e))?;
+    if !(-90.0..=90.0).contains(&lat) || !(-180.0..=180.0).contains(&lon) {
+        return Err(format!("{},{} is out of range", lat, lon));
+    }
+    Ok((lat, lon))
+}
+
+/// How a location was given on the command line.
+#[derive(Debug, Clone, PartialEq)]
+pub enum PlaceQuery {
+    /// A place id the API takes as is: numeric, `country/city` or
+    /// coordinates like `+47.6-122.3`
+    Id(String),
+    /// Free text such as "Seattle, WA", looked up by name
+    Name(String),
+    Coords(f64, f64),
+}
+
+impl PlaceQuery {
+    pub fn parse(location: &str) -> Self {
+        let location = location.trim();
+        let is_id = location.chars().all(|c| c.is_ascii_digit())
+            || location.contains('/')
+            || location.starts_with(['+', '-']);
+        if is_id {
+            PlaceQuery::Id(location.to_string())
+        } else {
+            PlaceQuery::Name(location.to_string())
+        }
+    }
+
+    /// The coordinate place id, e.g. `+47.6-122.3`.
+    pub fn coords_id(lat: f64, lon: f64) -> String {
+        format!("{:+}{:+}", lat, lon)
+    }
+
+    /// The places lookup for this query.
+    pub fn places_request(&self) -> PlacesRequest {
+        let request = match self {
+            PlaceQuery::Id(id) => PlacesRequest::new().with_placeid(id.as_str()),
+            PlaceQuery::Coords(lat, lon) => {
+                PlacesRequest::new().with_placeid(Self::coords_id(*lat, *lon))
+            }
+            PlaceQuery::Name(name) => PlacesRequest::new()
+                .set_query(name.as_str())
+                .set_qlimit(QUERY_LIMIT),
+        };
+        request.set_geo(true).set_lang("en")
+    }
+}
+
+impl fmt::Display for PlaceQuery {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            PlaceQuery::Id(id) => write!(f, "{}", id),
+            PlaceQuery::Name(name) => write!(f, "{:?}", name),
+            PlaceQuery::Coords(lat, lon) => write!(f, "{},{}", lat, lon),
+        }
+    }
+}
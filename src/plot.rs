@@ -0,0 +1,510 @@
+use std::ops::Range;
+use std::path::Path;
+
+use plotters::coord::types::{RangedCoordf64, RangedCoordi32};
+use plotters::coord::Shift;
+use plotters::element::DashedPathElement;
+use plotters::prelude::*;
+
+use crate::day::{Dataset, Quantity, Unit};
+
+mod band;
+mod eot;
+mod rate;
+
+pub use band::draw_band_chart;
+pub use eot::draw_eot_chart;
+pub use rate::draw_rate_chart;
+
+type DayChart<'a, 'b, DB> = ChartContext<'a, DB, Cartesian2d<RangedCoordi32, RangedCoordf64>>;
+
+/// Which chart json2day renders.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Chart {
+    /// Times and day length per day with their fitted sines
+    Line,
+    /// Shaded daylight band on a 24 h axis, like an almanac sun-graph
+    Band,
+    /// Sunrise/sunset azimuth and noon altitude with their fitted sines
+    Angles,
+    /// Day-to-day change of the fitted day length, sunrise and sunset
+    Rates,
+    /// Equation of time from the solar noons, with the analemma when noon
+    /// altitudes are known
+    Eot,
+}
+
+/// The value (y) axis of a line chart and the quantities that go on it.
+struct ValueAxis {
+    unit: Unit,
+    range: Range<f64>,
+    desc: &'static str,
+}
+
+impl ValueAxis {
+    /// Hours always span the full day; angles are fitted to the data in
+    /// steps of 10°.
+    fn new(datasets: &[Dataset], unit: Unit) -> Self {
+        match unit {
+            Unit::Hours => ValueAxis {
+                unit,
+                range: 0.0..24.0,
+                desc: "Time/Duration (h)",
+            },
+            Unit::Degrees => {
+                let (min, max) = datasets
+                    .iter()
+                    .flat_map(|dataset| {
+                        dataset
+                            .quantities_in(unit)
+                            .into_iter()
+                            .flat_map(|quantity| dataset.series(quantity))
+                    })
+                    .fold((f64::MAX, f64::MIN), |(min, max), (value, _)| {
+                        (min.min(value), max.max(value))
+                    });
+                let range = if min > max {
+                    0.0..90.0
+                } else {
+                    (min / 10.0).floor() * 10.0..(max / 10.0).ceil() * 10.0
+                };
+                ValueAxis {
+                    unit,
+                    range,
+                    desc: "Angle (°)",
+                }
+            }
+        }
+    }
+}
+
+/// How several datasets share the drawing area.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum Layout {
+    /// All locations on one chart
+    Overlay,
+    /// One panel per location
+    Grid,
+}
+
+/// Colors assigned to locations when more than one dataset is drawn.
+const LOCATION_COLORS: [RGBColor; 6] = [
+    RGBColor(228, 26, 28),
+    RGBColor(55, 126, 184),
+    RGBColor(77, 175, 74),
+    RGBColor(152, 78, 163),
+    RGBColor(255, 127, 0),
+    RGBColor(166, 86, 40),
+];
+
+pub fn quantity_color(quantity: Quantity) -> RGBColor {
+    match quantity {
+        Quantity::Sunrise => RGBColor(255, 0, 0),
+        Quantity::Sunset => RGBColor(0, 255, 0),
+        Quantity::SolarNoon => RGBColor(0, 0, 255),
+        Quantity::DayLength => RGBColor(0, 0, 0),
+        Quantity::CivilDawn => RGBColor(255, 140, 0),
+        Quantity::CivilDusk => RGBColor(148, 0, 211),
+        Quantity::NauticalDawn => RGBColor(205, 133, 63),
+        Quantity::NauticalDusk => RGBColor(106, 90, 205),
+        Quantity::AstronomicalDawn => RGBColor(139, 69, 19),
+        Quantity::AstronomicalDusk => RGBColor(25, 25, 112),
+        Quantity::RiseAzimuth => RGBColor(255, 0, 0),
+        Quantity::SetAzimuth => RGBColor(0, 255, 0),
+        Quantity::NoonAltitude => RGBColor(0, 0, 255),
+        Quantity::Moonrise => RGBColor(105, 105, 105),
+        Quantity::Moonset => RGBColor(160, 82, 45),
+        Quantity::MoonTransit => RGBColor(72, 61, 139),
+    }
+}
+
+pub fn location_color(index: usize) -> RGBColor {
+    LOCATION_COLORS[index % LOCATION_COLORS.len()]
+}
+
+/// Dash pattern `(dash, gap)` telling quantities apart when color is used
+/// for the location; `None` is a solid line.
+pub fn quantity_dash(quantity: Quantity) -> Option<(u32, u32)> {
+    match quantity {
+        Quantity::Sunrise | Quantity::RiseAzimuth | Quantity::Moonrise => None,
+        Quantity::Sunset | Quantity::SetAzimuth | Quantity::Moonset => Some((10, 5)),
+        Quantity::SolarNoon | Quantity::NoonAltitude | Quantity::MoonTransit => Some((2, 4)),
+        Quantity::DayLength => Some((20, 6)),
+        Quantity::CivilDawn | Quantity::CivilDusk => Some((14, 4)),
+        Quantity::NauticalDawn | Quantity::NauticalDusk => Some((6, 6)),
+        Quantity::AstronomicalDawn | Quantity::AstronomicalDusk => Some((1, 5)),
+    }
+}
+
+/// Dash (size, spacing) of the curves of further models fitted for
+/// comparison.
+const COMPARISON_DASH: (u32, u32) = (8, 6);
+
+/// Points of the curves fitted to a quantity over the days the dataset
+/// spans, the one shown as its fit first.
+fn fit_curves(dataset: &Dataset, quantity: Quantity) -> Vec<Vec<(i32, f64)>> {
+    dataset
+        .fits_of(quantity)
+        .map(|fitment| {
+            (0..dataset.axis_days() as i32)
+                .map(|x| (x, fitment.eval(x as f64)))
+                .collect()
+        })
+        .collect()
+}
+
+fn draw_styled_line<DB: DrawingBackend>(
+    chart: &mut DayChart<DB>,
+    points: Vec<(i32, f64)>,
+    style: ShapeStyle,
+    dash: Option<(u32, u32)>,
+) {
+    match dash {
+        None => {
+            chart.draw_series(LineSeries::new(points, style)).unwrap();
+        }
+        Some((size, spacing)) => {
+            chart
+                .draw_series(DashedLineSeries::new(points, size, spacing, style))
+                .unwrap();
+        }
+    }
+}
+
+fn build_day_chart<'a, 'b, DB: DrawingBackend>(
+    area: &'a DrawingArea<DB, Shift>,
+    caption: &str,
+    caption_size: u32,
+    axis: &ValueAxis,
+    day_axis: &str,
+    days: u64,
+) -> DayChart<'a, 'b, DB> {
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, ("sans-serif", caption_size).into_font())
+        .margin(5)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0..days as i32, axis.range.clone())
+        .unwrap();
+
+    chart
+        .configure_mesh()
+        .x_labels(28)
+        .y_labels(5)
+        .x_desc(day_axis)
+        .y_desc(axis.desc)
+        .draw()
+        .unwrap();
+
+    chart
+}
+
+/// The classic single-location chart: one color per quantity, samples
+/// joined by lines and the fitted sines on top.
+fn draw_single<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    dataset: &Dataset,
+    axis: &ValueAxis,
+) {
+    let mut chart = build_day_chart(
+        area,
+        caption,
+        50,
+        axis,
+        &dataset.day_axis(),
+        dataset.axis_days(),
+    );
+
+    for quantity in dataset.quantities_in(axis.unit) {
+        draw_prediction_band(&mut chart, dataset, quantity, quantity_color(quantity));
+    }
+
+    for quantity in dataset.quantities_in(axis.unit) {
+        let color = quantity_color(quantity);
+        for (index, segment) in dataset.segments(quantity).into_iter().enumerate() {
+            let series = chart
+                .draw_series(
+                    LineSeries::new(
+                        segment.into_iter().map(|(value, day)| (day as i32, value)),
+                        color,
+                    )
+                    .point_size(5),
+                )
+                .unwrap();
+            if index == 0 {
+                series
+                    .label(quantity.label())
+                    .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color));
+            }
+        }
+        chart
+            .draw_series(outlier_markers(dataset, quantity, color.into()))
+            .unwrap();
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperRight)
+        .draw()
+        .unwrap();
+
+    for quantity in dataset.quantities_in(axis.unit) {
+        for (index, points) in fit_curves(dataset, quantity).into_iter().enumerate() {
+            if index == 0 {
+                chart
+                    .draw_series(LineSeries::new(points, quantity_color(quantity)))
+                    .unwrap()
+                    .label(format!("{} Fitment", quantity.label()));
+            } else {
+                draw_styled_line(
+                    &mut chart,
+                    points,
+                    quantity_color(quantity).into(),
+                    Some(COMPARISON_DASH),
+                );
+            }
+        }
+    }
+}
+
+/// Shade the prediction band around the quantity's fit, if it has one.
+fn draw_prediction_band<DB: DrawingBackend>(
+    chart: &mut DayChart<DB>,
+    dataset: &Dataset,
+    quantity: Quantity,
+    color: RGBColor,
+) {
+    let band = dataset.prediction_band(quantity);
+    if band.is_empty() {
+        return;
+    }
+    let outline: Vec<(i32, f64)> = band
+        .iter()
+        .map(|&(x, _, high)| (x as i32, high))
+        .chain(band.iter().rev().map(|&(x, low, _)| (x as i32, low)))
+        .collect();
+    chart
+        .draw_series(std::iter::once(Polygon::new(
+            outline,
+            color.mix(0.15).filled(),
+        )))
+        .unwrap();
+}
+
+/// Crosses over the samples of `quantity` flagged as outliers.
+fn outlier_markers(
+    dataset: &Dataset,
+    quantity: Quantity,
+    color: RGBAColor,
+) -> impl Iterator<Item = Cross<(i32, f64), i32>> + '_ {
+    dataset
+        .series(quantity)
+        .into_iter()
+        .filter(move |&(_, day)| dataset.is_outlier(quantity, day))
+        .map(move |(value, day)| Cross::new((day as i32, value), 7, color.stroke_width(2)))
+}
+
+/// Draw the given datasets (with their location indices) on one chart,
+/// colored by location and dashed by quantity.
+fn draw_locations<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    caption_size: u32,
+    datasets: &[(usize, &Dataset)],
+    axis: &ValueAxis,
+) {
+    let day_axis = match datasets {
+        [(_, dataset)] => dataset.day_axis(),
+        _ => "Day".to_string(),
+    };
+    let days = datasets
+        .iter()
+        .map(|(_, dataset)| dataset.axis_days())
+        .max()
+        .unwrap_or(365);
+    let mut chart = build_day_chart(area, caption, caption_size, axis, &day_axis, days);
+
+    for &(index, dataset) in datasets {
+        for quantity in dataset.quantities_in(axis.unit) {
+            draw_prediction_band(&mut chart, dataset, quantity, location_color(index));
+        }
+    }
+
+    for &(index, dataset) in datasets {
+        let color = location_color(index);
+        for quantity in dataset.quantities_in(axis.unit) {
+            chart
+                .draw_series(
+                    dataset
+                        .series(quantity)
+                        .into_iter()
+                        .map(|(value, day)| Circle::new((day as i32, value), 3, color.filled())),
+                )
+                .unwrap();
+            chart
+                .draw_series(outlier_markers(dataset, quantity, color.into()))
+                .unwrap();
+
+            // moon times get joined within their runs instead of a fit
+            if !quantity.sinusoidal() {
+                for segment in dataset.segments(quantity) {
+                    draw_styled_line(
+                        &mut chart,
+                        segment
+                            .into_iter()
+                            .map(|(value, day)| (day as i32, value))
+                            .collect(),
+                        color.stroke_width(1),
+                        quantity_dash(quantity),
+                    );
+                }
+            }
+
+            // further models thinner than the one shown as the fit
+            for (index, points) in fit_curves(dataset, quantity).into_iter().enumerate() {
+                draw_styled_line(
+                    &mut chart,
+                    points,
+                    color.stroke_width(if index == 0 { 2 } else { 1 }),
+                    quantity_dash(quantity),
+                );
+            }
+        }
+    }
+}
+
+const LEGEND_ROW: i32 = 20;
+const LEGEND_COLUMN: i32 = 180;
+
+/// Legend entries per row across the chart width.
+fn legend_columns(width: u32) -> usize {
+    ((width as i32 - 20) / LEGEND_COLUMN).max(1) as usize
+}
+
+/// Quantities in `unit` present in any of the datasets, in `Quantity::ALL`
+/// order.
+fn shared_quantities(datasets: &[Dataset], unit: Unit) -> Vec<Quantity> {
+    let present: Vec<Vec<Quantity>> = datasets
+        .iter()
+        .map(|dataset| dataset.quantities_in(unit))
+        .collect();
+    Quantity::ALL
+        .into_iter()
+        .filter(|quantity| present.iter().any(|q| q.contains(quantity)))
+        .collect()
+}
+
+/// Height of the shared legend strip: location rows, then quantity rows.
+fn legend_height(datasets: &[Dataset], unit: Unit, width: u32) -> u32 {
+    let columns = legend_columns(width);
+    let rows = datasets.len().div_ceil(columns)
+        + shared_quantities(datasets, unit).len().div_ceil(columns);
+    (rows as i32 * LEGEND_ROW + LEGEND_ROW) as u32
+}
+
+/// Location swatches followed by the quantity line styles, wrapped to the
+/// strip width.
+fn draw_shared_legend<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    datasets: &[Dataset],
+    unit: Unit,
+) {
+    let font = ("sans-serif", 15).into_font();
+    let columns = legend_columns(area.dim_in_pixel().0);
+    let position = |slot: usize, first_row: usize| {
+        let x = 20 + LEGEND_COLUMN * (slot % columns) as i32;
+        let y = LEGEND_ROW * (first_row + slot / columns + 1) as i32;
+        (x, y)
+    };
+
+    for (index, dataset) in datasets.iter().enumerate() {
+        let (x, y) = position(index, 0);
+        area.draw(&Rectangle::new(
+            [(x, y - 5), (x + 30, y + 5)],
+            location_color(index).filled(),
+        ))
+        .unwrap();
+        area.draw(&Text::new(
+            dataset.label.clone(),
+            (x + 40, y - 7),
+            font.clone(),
+        ))
+        .unwrap();
+    }
+
+    let location_rows = datasets.len().div_ceil(columns);
+    for (index, quantity) in shared_quantities(datasets, unit).into_iter().enumerate() {
+        let (x, y) = position(index, location_rows);
+        let style = BLACK.stroke_width(2);
+        match quantity_dash(quantity) {
+            None => area
+                .draw(&PathElement::new(vec![(x, y), (x + 30, y)], style))
+                .unwrap(),
+            Some((size, spacing)) => area
+                .draw(&DashedPathElement::new(
+                    vec![(x, y), (x + 30, y)],
+                    size,
+                    spacing,
+                    style,
+                ))
+                .unwrap(),
+        }
+        area.draw(&Text::new(quantity.label(), (x + 40, y - 7), font.clone()))
+            .unwrap();
+    }
+}
+
+/// Split an area into a near-square grid with room for `count` panels.
+fn grid_panels<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    count: usize,
+) -> Vec<DrawingArea<DB, Shift>> {
+    let cols = (count as f64).sqrt().ceil() as usize;
+    let rows = count.div_ceil(cols);
+    area.split_evenly((rows, cols))
+}
+
+/// Render the line chart of one or more datasets to an SVG file, plotting
+/// the quantities measured in `unit`.
+pub fn draw_line_chart(
+    output: &Path,
+    caption: &str,
+    datasets: &[Dataset],
+    layout: Layout,
+    unit: Unit,
+) {
+    let root = SVGBackend::new(output, (1024, 768)).into_drawing_area();
+    let axis = ValueAxis::new(datasets, unit);
+
+    root.fill(&WHITE).unwrap();
+
+    if let [dataset] = datasets {
+        draw_single(&root, caption, dataset, &axis);
+    } else {
+        let (width, height) = root.dim_in_pixel();
+        let (charts, legend) = root.split_vertically(height - legend_height(datasets, unit, width));
+
+        match layout {
+            Layout::Overlay => {
+                let indexed: Vec<_> = datasets.iter().enumerate().collect();
+                draw_locations(&charts, caption, 40, &indexed, &axis);
+            }
+            Layout::Grid => {
+                let charts = charts
+                    .titled(caption, ("sans-serif", 40).into_font())
+                    .unwrap();
+                let panels = grid_panels(&charts, datasets.len());
+                for (index, (panel, dataset)) in panels.iter().zip(datasets).enumerate() {
+                    draw_locations(panel, &dataset.label, 25, &[(index, dataset)], &axis);
+                }
+            }
+        }
+
+        draw_shared_legend(&legend, datasets, unit);
+    }
+
+    root.present().unwrap();
+}
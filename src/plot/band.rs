@@ -0,0 +1,153 @@
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use super::grid_panels;
+use crate::day::{Dataset, Quantity};
+
+/// A shaded region between two times of day.
+struct Band {
+    label: &'static str,
+    lower: Quantity,
+    upper: Quantity,
+    color: RGBColor,
+}
+
+/// Painted in order, so the wider twilight bands come before the ones they
+/// surround; bands whose times are missing from the data are skipped.
+const BANDS: [Band; 4] = [
+    Band {
+        label: "Astronomical Twilight",
+        lower: Quantity::AstronomicalDawn,
+        upper: Quantity::AstronomicalDusk,
+        color: RGBColor(45, 50, 105),
+    },
+    Band {
+        label: "Nautical Twilight",
+        lower: Quantity::NauticalDawn,
+        upper: Quantity::NauticalDusk,
+        color: RGBColor(75, 90, 160),
+    },
+    Band {
+        label: "Civil Twilight",
+        lower: Quantity::CivilDawn,
+        upper: Quantity::CivilDusk,
+        color: RGBColor(140, 160, 215),
+    },
+    Band {
+        label: "Daylight",
+        lower: Quantity::Sunrise,
+        upper: Quantity::Sunset,
+        color: RGBColor(255, 236, 139),
+    },
+];
+
+const NIGHT: RGBColor = RGBColor(25, 25, 60);
+const NOON: RGBColor = RGBColor(255, 140, 0);
+
+/// Polygon enclosing a band: along the upper edge, then back along the lower
+/// edge, over the days that have both times.
+fn band_outline(dataset: &Dataset, band: &Band) -> Vec<(i32, f64)> {
+    let mut edges: Vec<(u64, f64, f64)> = dataset
+        .days
+        .iter()
+        .filter_map(|day| {
+            let lower = band.lower.get(day)?.get_num();
+            let upper = band.upper.get(day)?.get_num();
+            Some((dataset.day_number(day), lower, upper))
+        })
+        .collect();
+    edges.sort_by_key(|(day, _, _)| *day);
+
+    let upper = edges.iter().map(|(day, _, upper)| (*day as i32, *upper));
+    let lower = edges
+        .iter()
+        .rev()
+        .map(|(day, lower, _)| (*day as i32, *lower));
+
+    upper.chain(lower).collect()
+}
+
+fn draw_panel<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    caption_size: u32,
+    dataset: &Dataset,
+) {
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, ("sans-serif", caption_size).into_font())
+        .margin(5)
+        .x_label_area_size(40)
+        .y_label_area_size(50)
+        .build_cartesian_2d(0..dataset.axis_days() as i32, 0.0..24.0)
+        .unwrap();
+
+    chart.plotting_area().fill(&NIGHT).unwrap();
+
+    for band in &BANDS {
+        let outline = band_outline(dataset, band);
+        if outline.len() < 4 {
+            continue;
+        }
+
+        let color = band.color;
+        chart
+            .draw_series(std::iter::once(Polygon::new(outline, color.filled())))
+            .unwrap()
+            .label(band.label)
+            .legend(move |(x, y)| Rectangle::new([(x, y - 5), (x + 20, y + 5)], color.filled()));
+    }
+
+    chart
+        .draw_series(LineSeries::new(
+            dataset
+                .series(Quantity::SolarNoon)
+                .into_iter()
+                .map(|(value, day)| (day as i32, value)),
+            NOON.stroke_width(2),
+        ))
+        .unwrap()
+        .label(Quantity::SolarNoon.label())
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], NOON.stroke_width(2)));
+
+    chart
+        .configure_mesh()
+        .x_labels(28)
+        .y_labels(13)
+        .y_label_formatter(&|h| format!("{:02}:00", *h as u32))
+        .light_line_style(TRANSPARENT)
+        .bold_line_style(WHITE.mix(0.2))
+        .x_desc(dataset.day_axis())
+        .y_desc("Time of Day")
+        .draw()
+        .unwrap();
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperRight)
+        .draw()
+        .unwrap();
+}
+
+/// Render the daylight band chart ("sun-graph"), one panel per dataset.
+pub fn draw_band_chart(output: &Path, caption: &str, datasets: &[Dataset]) {
+    let root = SVGBackend::new(output, (1024, 768)).into_drawing_area();
+
+    root.fill(&WHITE).unwrap();
+
+    if let [dataset] = datasets {
+        draw_panel(&root, caption, 50, dataset);
+    } else {
+        let charts = root
+            .titled(caption, ("sans-serif", 40).into_font())
+            .unwrap();
+        for (panel, dataset) in grid_panels(&charts, datasets.len()).iter().zip(datasets) {
+            draw_panel(panel, &dataset.label, 25, dataset);
+        }
+    }
+
+    root.present().unwrap();
+}
@@ -0,0 +1,179 @@
+use std::path::Path;
+
+use plotters::coord::Shift;
+use plotters::prelude::*;
+
+use super::quantity_color;
+use crate::day::{Dataset, Quantity};
+use crate::eot::EquationOfTime;
+use crate::solar;
+
+/// Minutes either side of zero on the equation-of-time axes.
+const EOT_RANGE: f64 = 20.0;
+
+/// The derived equation of time over the days the dataset spans: samples,
+/// fit and, in grey, the solar calculator's.
+fn draw_equation<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption: &str,
+    caption_size: u32,
+    dataset: &Dataset,
+    equation: &EquationOfTime,
+) {
+    let color = quantity_color(Quantity::SolarNoon);
+    let days = dataset.axis_days() as i32;
+    let mut chart = ChartBuilder::on(area)
+        .caption(caption, ("sans-serif", caption_size).into_font())
+        .margin(5)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0..days, -EOT_RANGE..EOT_RANGE)
+        .unwrap();
+    chart
+        .configure_mesh()
+        .x_labels(14)
+        .y_labels(9)
+        .x_desc(dataset.day_axis())
+        .y_desc("Equation of time (min)")
+        .draw()
+        .unwrap();
+
+    let new_year = solar::julian_day(dataset.start_year, 1, 1);
+    chart
+        .draw_series(LineSeries::new(
+            (0..days).map(|x| (x, solar::position(new_year + x as f64 - 0.5).eot_minutes)),
+            BLACK.mix(0.3).stroke_width(1),
+        ))
+        .unwrap()
+        .label("Solar calculator")
+        .legend(|(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], BLACK.mix(0.3)));
+    chart
+        .draw_series(LineSeries::new(
+            (0..days).map(|x| (x, equation.eval(x as f64))),
+            color.stroke_width(2),
+        ))
+        .unwrap()
+        .label("From the solar noons")
+        .legend(move |(x, y)| PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2)));
+    chart
+        .draw_series(
+            equation
+                .samples
+                .iter()
+                .map(|&(day, minutes)| Circle::new((day as i32, minutes), 3, color.filled())),
+        )
+        .unwrap();
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperLeft)
+        .draw()
+        .unwrap();
+}
+
+/// Noon altitude against the equation of time, the samples joined in day
+/// order.
+fn draw_analemma<DB: DrawingBackend>(
+    area: &DrawingArea<DB, Shift>,
+    caption_size: u32,
+    points: &[(f64, f64, u64)],
+) {
+    let color = quantity_color(Quantity::NoonAltitude);
+    let (min, max) = points
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(min, max), &(_, altitude, _)| {
+            (min.min(altitude), max.max(altitude))
+        });
+    let mut chart = ChartBuilder::on(area)
+        .caption("Analemma", ("sans-serif", caption_size).into_font())
+        .margin(5)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(
+            -EOT_RANGE..EOT_RANGE,
+            (min / 10.0).floor() * 10.0..(max / 10.0).ceil() * 10.0,
+        )
+        .unwrap();
+    chart
+        .configure_mesh()
+        .x_labels(9)
+        .y_labels(9)
+        .x_desc("Equation of time (min)")
+        .y_desc(format!("{} (°)", Quantity::NoonAltitude.label()))
+        .draw()
+        .unwrap();
+
+    chart
+        .draw_series(LineSeries::new(
+            points
+                .iter()
+                .map(|&(minutes, altitude, _)| (minutes, altitude)),
+            color,
+        ))
+        .unwrap();
+    chart
+        .draw_series(
+            points
+                .iter()
+                .map(|&(minutes, altitude, _)| Circle::new((minutes, altitude), 3, color.filled())),
+        )
+        .unwrap();
+}
+
+/// One dataset's row of the chart.
+struct EotRow<'a> {
+    dataset: &'a Dataset,
+    equation: EquationOfTime,
+    analemma: Vec<(f64, f64, u64)>,
+}
+
+/// Render the equation of time derived from each dataset's solar noons,
+/// one row per dataset, with its analemma alongside when the dataset has
+/// noon altitudes. Datasets without enough solar noons are left out.
+pub fn draw_eot_chart(output: &Path, caption: &str, datasets: &[Dataset]) {
+    let root = SVGBackend::new(output, (1024, 768)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+
+    let rows: Vec<EotRow> = datasets
+        .iter()
+        .filter_map(|dataset| {
+            let equation = EquationOfTime::derive(dataset)?;
+            let analemma = equation.analemma(dataset);
+            Some(EotRow {
+                dataset,
+                equation,
+                analemma,
+            })
+        })
+        .collect();
+    let columns = if rows.iter().any(|row| !row.analemma.is_empty()) {
+        2
+    } else {
+        1
+    };
+
+    let (area, caption_size) = match rows.len() {
+        0 | 1 => (root.clone(), 40),
+        _ => (
+            root.titled(caption, ("sans-serif", 40).into_font())
+                .unwrap(),
+            25,
+        ),
+    };
+    let panels = area.split_evenly((rows.len().max(1), columns));
+    for (row, panels) in rows.iter().zip(panels.chunks(columns)) {
+        let title = if rows.len() == 1 {
+            caption
+        } else {
+            &row.dataset.label
+        };
+        draw_equation(&panels[0], title, caption_size, row.dataset, &row.equation);
+        if !row.analemma.is_empty() {
+            draw_analemma(&panels[1], caption_size, &row.analemma);
+        }
+    }
+
+    root.present().unwrap();
+}
@@ -0,0 +1,113 @@
+use std::path::Path;
+
+use plotters::prelude::*;
+
+use super::{draw_styled_line, location_color, quantity_color, quantity_dash};
+use crate::day::{Dataset, Quantity};
+
+/// The quantities whose change rates are drawn.
+const RATES: [Quantity; 3] = [Quantity::DayLength, Quantity::Sunrise, Quantity::Sunset];
+
+/// One dataset's change rate of one quantity, as drawn.
+struct RateCurve {
+    label: String,
+    color: RGBColor,
+    dash: Option<(u32, u32)>,
+    points: Vec<(i32, f64)>,
+}
+
+/// Render the change per day of the fitted day length, sunrise and sunset
+/// (minutes/day) of one or more datasets on one chart: colored by quantity
+/// for one dataset, by location (dashed by quantity) for several.
+pub fn draw_rate_chart(output: &Path, caption: &str, datasets: &[Dataset]) {
+    let root = SVGBackend::new(output, (1024, 768)).into_drawing_area();
+    root.fill(&WHITE).unwrap();
+
+    let multiple = datasets.len() > 1;
+    let curves: Vec<RateCurve> = datasets
+        .iter()
+        .enumerate()
+        .flat_map(|(index, dataset)| {
+            RATES.into_iter().filter_map(move |quantity| {
+                let points = (1..=dataset.axis_days())
+                    .map(|day| Some((day as i32, dataset.change_rate(quantity, day)?)))
+                    .collect::<Option<Vec<_>>>()?;
+                Some(if multiple {
+                    RateCurve {
+                        label: format!("{} {}", dataset.label, quantity.label()),
+                        color: location_color(index),
+                        dash: quantity_dash(quantity),
+                        points,
+                    }
+                } else {
+                    RateCurve {
+                        label: quantity.label().to_string(),
+                        color: quantity_color(quantity),
+                        dash: None,
+                        points,
+                    }
+                })
+            })
+        })
+        .collect();
+
+    let days = datasets.iter().map(Dataset::axis_days).max().unwrap_or(365);
+    let (min, max) = curves
+        .iter()
+        .flat_map(|curve| &curve.points)
+        .fold((0.0f64, 0.0f64), |(min, max), &(_, rate)| {
+            (min.min(rate), max.max(rate))
+        });
+
+    let mut chart = ChartBuilder::on(&root)
+        .caption(caption, ("sans-serif", 50).into_font())
+        .margin(5)
+        .x_label_area_size(40)
+        .y_label_area_size(40)
+        .build_cartesian_2d(0..days as i32, min.floor() - 1.0..max.ceil() + 1.0)
+        .unwrap();
+
+    chart
+        .configure_mesh()
+        .x_labels(28)
+        .y_labels(10)
+        .x_desc(match datasets {
+            [dataset] => dataset.day_axis(),
+            _ => "Day".to_string(),
+        })
+        .y_desc("Change (min/day)")
+        .draw()
+        .unwrap();
+
+    chart
+        .draw_series(LineSeries::new(vec![(0, 0.0), (days as i32, 0.0)], BLACK))
+        .unwrap();
+
+    for RateCurve {
+        label,
+        color,
+        dash,
+        points,
+    } in curves
+    {
+        draw_styled_line(&mut chart, points, color.stroke_width(2), dash);
+        // an empty series just for the legend entry
+        chart
+            .draw_series(LineSeries::new(std::iter::empty::<(i32, f64)>(), color))
+            .unwrap()
+            .label(label)
+            .legend(move |(x, y)| {
+                PathElement::new(vec![(x, y), (x + 20, y)], color.stroke_width(2))
+            });
+    }
+
+    chart
+        .configure_series_labels()
+        .background_style(WHITE.mix(0.8))
+        .border_style(BLACK)
+        .position(SeriesLabelPosition::UpperRight)
+        .draw()
+        .unwrap();
+
+    root.present().unwrap();
+}
@@ -0,0 +1,168 @@
+//! Local solar calculator after the NOAA solar spreadsheet: declination,
+//! equation of time, and the rise/set/noon times and angles that follow from
+//! them for an observer.
+
+/// Zenith of the sun's centre at sunrise/sunset: 90° plus refraction and the
+/// solar semi-diameter.
+pub const SUNRISE_ZENITH: f64 = 90.833;
+pub const CIVIL_ZENITH: f64 = 96.0;
+pub const NAUTICAL_ZENITH: f64 = 102.0;
+pub const ASTRONOMICAL_ZENITH: f64 = 108.0;
+
+/// Where the sun is on the celestial sphere at an instant.
+#[derive(Debug, Clone, Copy)]
+pub struct SolarPosition {
+    /// Declination in degrees
+    pub declination: f64,
+    /// Equation of time (apparent minus mean solar time) in minutes
+    pub eot_minutes: f64,
+}
+
+/// Rise, set and transit of the sun on one local date, in local hours for
+/// the given UTC offset. Rise/set related values are `None` on days when
+/// the sun does not cross the horizon.
+#[derive(Debug, Clone, Copy)]
+pub struct SolarDay {
+    pub noon: f64,
+    pub sunrise: Option<f64>,
+    pub sunset: Option<f64>,
+    /// Degrees from true north
+    pub rise_azimuth: Option<f64>,
+    pub set_azimuth: Option<f64>,
+    /// Maximum altitude of the sun's centre, at transit, in degrees
+    pub noon_altitude: f64,
+    /// (dawn, dusk) for each twilight
+    pub civil: Option<(f64, f64)>,
+    pub nautical: Option<(f64, f64)>,
+    pub astronomical: Option<(f64, f64)>,
+}
+
+/// Julian day at 0h UT of a Gregorian calendar date.
+pub fn julian_day(y: u64, m: u64, d: u64) -> f64 {
+    let (y, m) = if m <= 2 {
+        (y as f64 - 1.0, m as f64 + 12.0)
+    } else {
+        (y as f64, m as f64)
+    };
+    let a = (y / 100.0).floor();
+    let b = 2.0 - a + (a / 4.0).floor();
+    (365.25 * (y + 4716.0)).floor() + (30.6001 * (m + 1.0)).floor() + d as f64 + b - 1524.5
+}
+
+pub fn position(julian_day: f64) -> SolarPosition {
+    let jc = (julian_day - 2451545.0) / 36525.0;
+
+    let mean_long = (280.46646 + jc * (36000.76983 + jc * 0.0003032)).rem_euclid(360.0);
+    let mean_anom = 357.52911 + jc * (35999.05029 - 0.0001537 * jc);
+    let eccent = 0.016708634 - jc * (0.000042037 + 0.0000001267 * jc);
+
+    let m = mean_anom.to_radians();
+    let center = m.sin() * (1.914602 - jc * (0.004817 + 0.000014 * jc))
+        + (2.0 * m).sin() * (0.019993 - 0.000101 * jc)
+        + (3.0 * m).sin() * 0.000289;
+    let true_long = mean_long + center;
+    let omega = (125.04 - 1934.136 * jc).to_radians();
+    let app_long = true_long - 0.00569 - 0.00478 * omega.sin();
+
+    let mean_obliq =
+        23.0 + (26.0 + (21.448 - jc * (46.815 + jc * (0.00059 - jc * 0.001813))) / 60.0) / 60.0;
+    let obliq = (mean_obliq + 0.00256 * omega.cos()).to_radians();
+
+    let declination = (obliq.sin() * app_long.to_radians().sin())
+        .asin()
+        .to_degrees();
+
+    let var_y = (obliq / 2.0).tan().powi(2);
+    let l0 = mean_long.to_radians();
+    let eot = var_y * (2.0 * l0).sin() - 2.0 * eccent * m.sin()
+        + 4.0 * eccent * var_y * m.sin() * (2.0 * l0).cos()
+        - 0.5 * var_y * var_y * (4.0 * l0).sin()
+        - 1.25 * eccent * eccent * (2.0 * m).sin();
+
+    SolarPosition {
+        declination,
+        eot_minutes: 4.0 * eot.to_degrees(),
+    }
+}
+
+/// Hour angle in degrees at which the sun's centre reaches `zenith`, or
+/// `None` if it never does that day.
+pub fn hour_angle(latitude: f64, declination: f64, zenith: f64) -> Option<f64> {
+    let (lat, decl) = (latitude.to_radians(), declination.to_radians());
+    let cos_ha = zenith.to_radians().cos() / (lat.cos() * decl.cos()) - lat.tan() * decl.tan();
+    (-1.0..=1.0)
+        .contains(&cos_ha)
+        .then(|| cos_ha.acos().to_degrees())
+}
+
+/// Hours between sunrise and sunset for a declination: 0 in polar night,
+/// 24 in polar day.
+pub fn day_length(latitude: f64, declination: f64) -> f64 {
+    let (lat, decl) = (latitude.to_radians(), declination.to_radians());
+    let cos_ha =
+        SUNRISE_ZENITH.to_radians().cos() / (lat.cos() * decl.cos()) - lat.tan() * decl.tan();
+    2.0 * cos_ha.clamp(-1.0, 1.0).acos().to_degrees() / 15.0
+}
+
+/// Azimuth (degrees from north) of the rising sun at `zenith`; the setting
+/// azimuth mirrors it at 360° minus this.
+pub fn rise_azimuth(latitude: f64, declination: f64, zenith: f64) -> Option<f64> {
+    let (lat, decl) = (latitude.to_radians(), declination.to_radians());
+    let alt = (90.0 - zenith).to_radians();
+    let cos_az = (decl.sin() - alt.sin() * lat.sin()) / (alt.cos() * lat.cos());
+    (-1.0..=1.0)
+        .contains(&cos_az)
+        .then(|| cos_az.acos().to_degrees())
+}
+
+/// Altitude of the sun's centre at transit.
+pub fn noon_altitude(latitude: f64, declination: f64) -> f64 {
+    90.0 - (latitude - declination).abs()
+}
+
+/// Local solar times and angles for a date at `latitude`/`longitude`
+/// (degrees, east positive) and a UTC offset in hours.
+pub fn solar_day(
+    y: u64,
+    m: u64,
+    d: u64,
+    latitude: f64,
+    longitude: f64,
+    utc_offset: f64,
+) -> SolarDay {
+    let midnight = julian_day(y, m, d);
+    // position of the sun at a local time of that date
+    let at = |local: f64| position(midnight + (local - utc_offset) / 24.0);
+    let transit =
+        |pos: SolarPosition| 12.0 - longitude / 15.0 - pos.eot_minutes / 60.0 + utc_offset;
+
+    let noon_pos = at(12.0 - longitude / 15.0 + utc_offset);
+    let noon = transit(noon_pos);
+
+    // first pass with the noon position, then once more with the sun's
+    // position at the estimated event time
+    let event = |zenith: f64, sign: f64| {
+        let ha = hour_angle(latitude, noon_pos.declination, zenith)?;
+        let guess = at(noon + sign * ha / 15.0);
+        let ha = hour_angle(latitude, guess.declination, zenith)?;
+        Some((transit(guess) + sign * ha / 15.0, guess.declination))
+    };
+    let span = |zenith: f64| Some((event(zenith, -1.0)?.0, event(zenith, 1.0)?.0));
+
+    let rise = event(SUNRISE_ZENITH, -1.0);
+    let set = event(SUNRISE_ZENITH, 1.0);
+
+    SolarDay {
+        noon,
+        sunrise: rise.map(|(time, _)| time),
+        sunset: set.map(|(time, _)| time),
+        rise_azimuth: rise.and_then(|(_, decl)| rise_azimuth(latitude, decl, SUNRISE_ZENITH)),
+        set_azimuth: set
+            .and_then(|(_, decl)| rise_azimuth(latitude, decl, SUNRISE_ZENITH))
+            .map(|az| 360.0 - az),
+        noon_altitude: noon_altitude(latitude, noon_pos.declination),
+        civil: span(CIVIL_ZENITH),
+        nautical: span(NAUTICAL_ZENITH),
+        astronomical: span(ASTRONOMICAL_ZENITH),
+    }
+}
@@ -0,0 +1,237 @@
+use std::fmt::Write;
+
+use crate::day::{Dataset, Quantity, Unit};
+
+/// ANSI foreground color code for a quantity; dawn and dusk of the same
+/// twilight share a color.
+fn ansi_color(quantity: Quantity) -> u8 {
+    match quantity {
+        Quantity::Sunrise => 31,
+        Quantity::Sunset => 32,
+        Quantity::SolarNoon => 34,
+        Quantity::DayLength => 37,
+        Quantity::CivilDawn | Quantity::CivilDusk => 33,
+        Quantity::NauticalDawn | Quantity::NauticalDusk => 35,
+        Quantity::AstronomicalDawn | Quantity::AstronomicalDusk => 36,
+        Quantity::RiseAzimuth => 31,
+        Quantity::SetAzimuth => 32,
+        Quantity::NoonAltitude => 34,
+        Quantity::Moonrise => 90,
+        Quantity::Moonset => 91,
+        Quantity::MoonTransit => 94,
+    }
+}
+
+/// Width taken by the hour labels and the axis line.
+const Y_LABEL_WIDTH: usize = 7;
+
+/// A grid of braille cells, each holding 2x4 dots and one color.
+struct Canvas {
+    width: usize,
+    height: usize,
+    cells: Vec<u8>,
+    colors: Vec<u8>,
+}
+
+impl Canvas {
+    fn new(width: usize, height: usize) -> Self {
+        Canvas {
+            width,
+            height,
+            cells: vec![0; width * height],
+            colors: vec![0; width * height],
+        }
+    }
+
+    fn dot_width(&self) -> usize {
+        self.width * 2
+    }
+
+    fn dot_height(&self) -> usize {
+        self.height * 4
+    }
+
+    fn set(&mut self, x: i64, y: i64, color: u8) {
+        if x < 0 || y < 0 || x >= self.dot_width() as i64 || y >= self.dot_height() as i64 {
+            return;
+        }
+        let (x, y) = (x as usize, y as usize);
+        // braille dot numbering: left column 1,2,3,7 and right column 4,5,6,8
+        const BITS: [[u8; 4]; 2] = [[0x01, 0x02, 0x04, 0x40], [0x08, 0x10, 0x20, 0x80]];
+        let cell = (y / 4) * self.width + x / 2;
+        self.cells[cell] |= BITS[x % 2][y % 4];
+        self.colors[cell] = color;
+    }
+
+    fn line(&mut self, (x0, y0): (i64, i64), (x1, y1): (i64, i64), color: u8) {
+        let steps = (x1 - x0).abs().max((y1 - y0).abs()).max(1);
+        for step in 0..=steps {
+            let x = x0 + (x1 - x0) * step / steps;
+            let y = y0 + (y1 - y0) * step / steps;
+            self.set(x, y, color);
+        }
+    }
+
+    fn row(&self, row: usize, color: bool) -> String {
+        let mut out = String::new();
+        let mut current = 0;
+        for col in 0..self.width {
+            let cell = row * self.width + col;
+            let glyph = char::from_u32(0x2800 + self.cells[cell] as u32).unwrap();
+            if color && self.cells[cell] != 0 && self.colors[cell] != current {
+                current = self.colors[cell];
+                let _ = write!(out, "\x1b[{}m", current);
+            }
+            out.push(glyph);
+        }
+        if color && current != 0 {
+            out.push_str("\x1b[0m");
+        }
+        out
+    }
+}
+
+/// Render a dataset's samples and fitted sines as a braille chart that is
+/// `width` columns wide and roughly `height` rows tall.
+pub fn render_chart(
+    caption: &str,
+    dataset: &Dataset,
+    width: usize,
+    height: usize,
+    color: bool,
+) -> String {
+    let plot_width = width.saturating_sub(Y_LABEL_WIDTH + 1).max(10);
+    let plot_height = height.saturating_sub(4).max(6);
+    let mut canvas = Canvas::new(plot_width, plot_height);
+    let axis_days = dataset.axis_days();
+
+    let to_dot = |day: f64, hours: f64, canvas: &Canvas| -> (i64, i64) {
+        let x = day / axis_days as f64 * (canvas.dot_width() - 1) as f64;
+        let y = (24.0 - hours) / 24.0 * (canvas.dot_height() - 1) as f64;
+        (x.round() as i64, y.round() as i64)
+    };
+
+    let quantities = dataset.quantities_in(Unit::Hours);
+
+    for &quantity in &quantities {
+        let ansi = ansi_color(quantity);
+        if let Some(fitment) = dataset.fit(quantity) {
+            let mut last = None;
+            for x in 0..canvas.dot_width() {
+                let day = x as f64 / (canvas.dot_width() - 1) as f64 * axis_days as f64;
+                let dot = to_dot(day, fitment.eval(day), &canvas);
+                if let Some(last) = last {
+                    canvas.line(last, dot, ansi);
+                }
+                last = Some(dot);
+            }
+        }
+    }
+
+    // samples go on top as small 2x2 blobs so they stand out from the curves
+    for &quantity in &quantities {
+        let ansi = ansi_color(quantity);
+        for (value, day) in dataset.series(quantity) {
+            let (x, y) = to_dot(day as f64, value, &canvas);
+            for (dx, dy) in [(0, 0), (1, 0), (0, 1), (1, 1)] {
+                canvas.set(x + dx, y + dy, ansi);
+            }
+        }
+    }
+
+    let mut out = String::new();
+    let _ = writeln!(out, "{:^width$}", caption, width = width);
+
+    let label_row = |hours: usize| (24 - hours) * (plot_height - 1) / 24;
+    for row in 0..plot_height {
+        let label = [24, 18, 12, 6, 0]
+            .into_iter()
+            .find(|&hours| label_row(hours) == row)
+            .map(|hours| format!("{:02}:00 ┤", hours))
+            .unwrap_or_else(|| format!("{:>w$}", "│", w = Y_LABEL_WIDTH));
+        let _ = writeln!(out, "{}{}", label, canvas.row(row, color));
+    }
+
+    let tick_col = |day: usize| day * (plot_width - 1) / axis_days as usize;
+    let mut axis: Vec<char> = vec!['─'; plot_width];
+    let mut ticks: Vec<char> = vec![' '; plot_width + 4];
+    // every 60 days, spread out for datasets spanning several years
+    let tick_step = 60 * (axis_days as usize).div_ceil(366);
+    for day in (0..axis_days as usize).step_by(tick_step) {
+        let col = tick_col(day);
+        axis[col] = '┬';
+        for (i, c) in day.to_string().chars().enumerate() {
+            ticks[col + i] = c;
+        }
+    }
+    let _ = writeln!(
+        out,
+        "{:>w$}{}",
+        "└",
+        axis.into_iter().collect::<String>(),
+        w = Y_LABEL_WIDTH
+    );
+    let _ = writeln!(
+        out,
+        "{:w$}{}  (day)",
+        "",
+        ticks.into_iter().collect::<String>().trim_end(),
+        w = Y_LABEL_WIDTH
+    );
+
+    let legend: Vec<(usize, String)> = quantities
+        .iter()
+        .map(|quantity| {
+            let visible = quantity.label().chars().count() + 2;
+            if color {
+                let entry = format!(
+                    "\x1b[{}m⣿\x1b[0m {}",
+                    ansi_color(*quantity),
+                    quantity.label()
+                );
+                (visible, entry)
+            } else {
+                (visible, format!("⣿ {}", quantity.label()))
+            }
+        })
+        .collect();
+
+    // wrap the legend to the chart width
+    let mut line = String::new();
+    let mut line_width = Y_LABEL_WIDTH;
+    for (visible, entry) in legend {
+        if line_width > Y_LABEL_WIDTH && line_width + 3 + visible > width {
+            let _ = writeln!(out, "{:w$}{}", "", line, w = Y_LABEL_WIDTH);
+            line.clear();
+            line_width = Y_LABEL_WIDTH;
+        }
+        if line_width > Y_LABEL_WIDTH {
+            line.push_str("   ");
+            line_width += 3;
+        }
+        line.push_str(&entry);
+        line_width += visible;
+    }
+    let _ = writeln!(out, "{:w$}{}", "", line, w = Y_LABEL_WIDTH);
+
+    out
+}
+
+/// Terminal size in columns and rows, falling back to `$COLUMNS`/`$LINES`
+/// and then 80x24 when stdout isn't a terminal.
+pub fn terminal_size() -> (usize, usize) {
+    if let Some((terminal_size::Width(w), terminal_size::Height(h))) =
+        terminal_size::terminal_size()
+    {
+        return (w as usize, h as usize);
+    }
+
+    let env = |name: &str, default: usize| {
+        std::env::var(name)
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or(default)
+    };
+
+    (env("COLUMNS", 80), env("LINES", 24))
+}
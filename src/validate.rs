@@ -0,0 +1,339 @@
+//! Checks a dataset file before json2day trusts it: records that do not
+//! parse, dates that are duplicated, out of order or not in the calendar,
+//! times and angles out of range, and fields that contradict each other.
+
+use std::collections::HashMap;
+use std::fmt;
+use std::fs;
+use std::path::Path;
+
+use serde::Serialize;
+use serde_json::Value;
+
+use crate::day::{day_of_year, days_in_month, Day, Quantity, Unit};
+use crate::format::clock;
+use crate::place::AstroInfo;
+
+/// Hours `daylen` may differ from `sset - srise`: both ends are rounded to
+/// the minute.
+const DAYLEN_TOLERANCE: f64 = 1.5 / 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Severity {
+    /// The record is wrong; json2day would plot or fit nonsense
+    Error,
+    /// Unusual but usable, e.g. a missing sunset or days out of order
+    Warning,
+}
+
+/// How `--validate` and `--analytics` print their reports.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, clap::ValueEnum)]
+pub enum ReportFormat {
+    /// One `file:line: severity: message` line per issue
+    Human,
+    /// A JSON array with one report per input
+    Json,
+}
+
+#[derive(Debug, Serialize)]
+pub struct Issue {
+    /// Line of the input where the record starts (1-based)
+    pub line: usize,
+    /// Index of the record in the days list, if the issue is about one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub record: Option<usize>,
+    /// "YYYY-MM-DD" of the record, if it has one
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub date: Option<String>,
+    pub severity: Severity,
+    pub message: String,
+}
+
+/// Everything found wrong with one input file.
+#[derive(Debug, Serialize)]
+pub struct Report {
+    pub input: String,
+    pub records: usize,
+    pub errors: usize,
+    pub warnings: usize,
+    pub issues: Vec<Issue>,
+}
+
+impl Report {
+    pub fn check(path: &Path) -> Self {
+        let input = fs::read_to_string(path).expect("Unable to read file");
+        let mut report = Report {
+            input: path.display().to_string(),
+            records: 0,
+            errors: 0,
+            warnings: 0,
+            issues: Vec::new(),
+        };
+
+        let value: Value = match serde_json::from_str(&input) {
+            Ok(value) => value,
+            Err(err) => {
+                report.push(err.line(), None, None, Severity::Error, err.to_string());
+                return report;
+            }
+        };
+        let records = match &value {
+            Value::Array(records) => records,
+            Value::Object(file) => {
+                if let Err(err) = serde_json::from_value::<AstroInfo>(
+                    file.get("location").cloned().unwrap_or_default(),
+                ) {
+                    report.push(
+                        1,
+                        None,
+                        None,
+                        Severity::Error,
+                        format!("location header: {}", err),
+                    );
+                }
+                match file.get("days") {
+                    Some(Value::Array(records)) => records,
+                    _ => {
+                        report.push(1, None, None, Severity::Error, "no days list".to_string());
+                        return report;
+                    }
+                }
+            }
+            _ => {
+                report.push(
+                    1,
+                    None,
+                    None,
+                    Severity::Error,
+                    "expected a list of days, or a location header and days".to_string(),
+                );
+                return report;
+            }
+        };
+        report.records = records.len();
+        if records.is_empty() {
+            report.push(1, None, None, Severity::Error, "no days".to_string());
+        }
+
+        let lines = record_lines(&input);
+        let mut seen: HashMap<(u64, u64, u64), usize> = HashMap::new();
+        let mut last: Option<(u64, u64, u64)> = None;
+        for (index, record) in records.iter().enumerate() {
+            let line = lines.get(index).copied().unwrap_or(1);
+            let day: Day = match serde_json::from_value(record.clone()) {
+                Ok(day) => day,
+                Err(err) => {
+                    report.push(line, Some(index), None, Severity::Error, err.to_string());
+                    continue;
+                }
+            };
+            let date = format!("{:04}-{:02}-{:02}", day.y, day.m, day.d);
+            let mut push = |severity, message: String| {
+                report.push(line, Some(index), Some(date.clone()), severity, message)
+            };
+
+            let key = (day.y, day.m, day.d);
+            if day.m == 0 || day.d == 0 || day.d > days_in_month(day.y, day.m) {
+                push(Severity::Error, "not a calendar date".to_string());
+            } else {
+                let yday = day_of_year(day.y, day.m, day.d);
+                if record.get("yday").is_some() && day.yday != yday {
+                    push(
+                        Severity::Error,
+                        format!("yday {}, but {} by the calendar", day.yday, yday),
+                    );
+                }
+                match seen.get(&key) {
+                    Some(first) => push(Severity::Error, format!("duplicate of line {}", first)),
+                    None => {
+                        seen.insert(key, line);
+                    }
+                }
+                if let Some(previous) = last.filter(|previous| key < *previous) {
+                    push(
+                        Severity::Warning,
+                        format!(
+                            "out of order, after {:04}-{:02}-{:02}",
+                            previous.0, previous.1, previous.2
+                        ),
+                    );
+                }
+                last = Some(last.map_or(key, |previous| previous.max(key)));
+            }
+
+            let mut values: HashMap<&str, f64> = HashMap::new();
+            for quantity in Quantity::ALL {
+                let Some(value) = quantity.get(&day) else {
+                    continue;
+                };
+                let Some(number) = value.checked_num() else {
+                    push(
+                        Severity::Error,
+                        format!("{} \"{}\" is not a number", quantity.label(), value),
+                    );
+                    continue;
+                };
+                let range = match (quantity, quantity.unit()) {
+                    (Quantity::NoonAltitude, _) => -90.0..=90.0,
+                    (_, Unit::Degrees) => 0.0..=360.0,
+                    (_, Unit::Hours) => 0.0..=24.0,
+                };
+                if !range.contains(&number) {
+                    push(
+                        Severity::Error,
+                        format!("{} {} is out of range", quantity.label(), number),
+                    );
+                    continue;
+                }
+                values.insert(quantity.label(), number);
+            }
+            if let Some(millum) = &day.millum {
+                if !matches!(millum.checked_num(), Some(p) if (0.0..=100.0).contains(&p)) {
+                    push(
+                        Severity::Error,
+                        "moon illumination is not a percentage".to_string(),
+                    );
+                }
+            }
+
+            // moon records have no sun times to check
+            let moon = [&day.mrise, &day.mset, &day.mtransit]
+                .iter()
+                .any(|time| time.is_some())
+                || day.mphase.is_some();
+            if moon && day.srise.is_none() && day.sset.is_none() {
+                continue;
+            }
+
+            let srise = values.get(Quantity::Sunrise.label()).copied();
+            let sset = values.get(Quantity::Sunset.label()).copied();
+            if day.srise.is_none() {
+                push(Severity::Warning, "no sunrise".to_string());
+            }
+            if day.sset.is_none() {
+                push(Severity::Warning, "no sunset".to_string());
+            }
+            let (Some(srise), Some(sset)) = (srise, sset) else {
+                continue;
+            };
+            if srise >= sset {
+                push(
+                    Severity::Error,
+                    format!(
+                        "sunrise {} is not before sunset {}",
+                        clock(srise),
+                        clock(sset)
+                    ),
+                );
+                continue;
+            }
+            if let Some(&daylen) = values.get(Quantity::DayLength.label()) {
+                if (daylen - (sset - srise)).abs() > DAYLEN_TOLERANCE {
+                    push(
+                        Severity::Error,
+                        format!(
+                            "day length {}, but sunset - sunrise is {}",
+                            clock(daylen),
+                            clock(sset - srise)
+                        ),
+                    );
+                }
+            }
+            if let Some(&noon) = values.get(Quantity::SolarNoon.label()) {
+                if !(srise..=sset).contains(&noon) {
+                    push(
+                        Severity::Warning,
+                        format!("solar noon {} is outside daylight", clock(noon)),
+                    );
+                }
+            }
+        }
+
+        report
+    }
+
+    fn push(
+        &mut self,
+        line: usize,
+        record: Option<usize>,
+        date: Option<String>,
+        severity: Severity,
+        message: String,
+    ) {
+        match severity {
+            Severity::Error => self.errors += 1,
+            Severity::Warning => self.warnings += 1,
+        }
+        self.issues.push(Issue {
+            line,
+            record,
+            date,
+            severity,
+            message,
+        });
+    }
+}
+
+/// "data/seattle.json:12: error: 2024-01-03: yday 99, but 3 by the calendar",
+/// then a summary line.
+impl fmt::Display for Report {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        for issue in &self.issues {
+            let severity = match issue.severity {
+                Severity::Error => "error",
+                Severity::Warning => "warning",
+            };
+            write!(f, "{}:{}: {}: ", self.input, issue.line, severity)?;
+            if let Some(date) = &issue.date {
+                write!(f, "{}: ", date)?;
+            }
+            writeln!(f, "{}", issue.message)?;
+        }
+        writeln!(
+            f,
+            "{}: {} records, {} errors, {} warnings",
+            self.input, self.records, self.errors, self.warnings
+        )
+    }
+}
+
+/// Line (1-based) where each day record starts: the objects directly inside
+/// the first array of the file.
+fn record_lines(input: &str) -> Vec<usize> {
+    let mut lines = Vec::new();
+    let mut line = 1;
+    let mut depth = 0;
+    let mut days_depth = None;
+    let mut in_string = false;
+    let mut escaped = false;
+    for c in input.chars() {
+        if c == '\n' {
+            line += 1;
+        }
+        if in_string {
+            match c {
+                _ if escaped => escaped = false,
+                '\\' => escaped = true,
+                '"' => in_string = false,
+                _ => {}
+            }
+            continue;
+        }
+        match c {
+            '"' => in_string = true,
+            '[' | '{' => {
+                if c == '{' && days_depth == Some(depth) {
+                    lines.push(line);
+                }
+                depth += 1;
+                if c == '[' && days_depth.is_none() {
+                    days_depth = Some(depth);
+                }
+            }
+            ']' | '}' => depth -= 1,
+            _ => {}
+        }
+    }
+    lines
+}
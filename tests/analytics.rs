@@ -0,0 +1,104 @@
+use sun::analytics::{Analytics, Extreme};
+use sun::day::{day_of_year, days_in_month, Dataset, Day, NumOrStr, Quantity};
+use sun::fit::{Fitment, SineFitment, SineFitter};
+use sun::solar;
+
+#[test]
+fn reports_seasons_and_extremes_of_the_fits() {
+    let mut dataset = Dataset::parse(
+        r#"[
+            {"y": 2024, "m": 1, "d": 3, "daylen": 8.6},
+            {"y": 2024, "m": 7, "d": 3, "daylen": 15.8}
+        ]"#,
+        String::from("test"),
+    );
+    // longest on day 172 (20 June 2024), 12.2 ± 3.7 hours
+    let b = 2.0 * std::f64::consts::PI / 365.2422;
+    dataset.fits.push((
+        Quantity::DayLength,
+        Fitment::Sine(SineFitment {
+            period_mul_2pi: 1.0,
+            a: 3.7,
+            b,
+            c: 365.2422 / 4.0 - 172.0,
+            d: 12.2,
+        }),
+    ));
+
+    let analytics = Analytics::compute(&dataset);
+
+    assert_eq!(analytics.years.len(), 1);
+    let year = &analytics.years[0];
+    assert!(year.march_equinox.starts_with("2024-03-20"));
+    assert!(year.june_solstice.starts_with("2024-06-20"));
+    assert!(year.september_equinox.starts_with("2024-09-22"));
+    assert!(year.december_solstice.starts_with("2024-12-21"));
+    let longest = year.longest_day.as_ref().unwrap();
+    assert_eq!((longest.date.as_str(), longest.day), ("2024-06-20", 172));
+    assert!((longest.value - 15.9).abs() < 1e-3);
+    // too few days for the physical model or a Fourier series
+    assert_eq!(longest.model, "sine");
+    let gain = year.fastest_lengthening.as_ref().unwrap();
+    assert!((gain.value - 3.7 * b * 60.0).abs() < 0.01, "{}", gain.value);
+    assert!((80..=81).contains(&gain.day), "{}", gain.day);
+    assert!(year.earliest_sunrise.is_none());
+
+    let json = serde_json::to_string(&analytics).unwrap();
+    assert!(!json.contains("sunrise"));
+}
+
+#[test]
+fn sunrise_and_sunset_extremes_do_not_follow_a_sine() {
+    // Seattle every other day of 2024, on standard time
+    let days: Vec<Day> = (1..=12u64)
+        .flat_map(|m| (1..=days_in_month(2024, m)).map(move |d| (m, d)))
+        .step_by(2)
+        .map(|(m, d)| {
+            let sun = solar::solar_day(2024, m, d, 47.61, -122.33, -8.0);
+            Day {
+                y: 2024,
+                m,
+                d,
+                yday: day_of_year(2024, m, d),
+                srise: Some(NumOrStr::Num(sun.sunrise.unwrap())),
+                sset: Some(NumOrStr::Num(sun.sunset.unwrap())),
+                ..Default::default()
+            }
+        })
+        .collect();
+    let mut dataset = Dataset::from_days(String::from("Seattle"), days);
+    for quantity in [Quantity::Sunrise, Quantity::Sunset, Quantity::DayLength] {
+        let sine = SineFitter::fit_quietly(&dataset.series(quantity), 1.0).unwrap();
+        dataset.fits.push((quantity, Fitment::Sine(sine)));
+    }
+
+    let analytics = Analytics::compute(&dataset);
+
+    let year = &analytics.years[0];
+    let date = |extreme: &Option<Extreme>| extreme.as_ref().unwrap().date.clone();
+    let latest_sunrise = date(&year.latest_sunrise);
+    assert!(
+        ("2024-01-01".."2024-01-06").contains(&latest_sunrise.as_str()),
+        "{}",
+        latest_sunrise
+    );
+    let earliest_sunset = date(&year.earliest_sunset);
+    assert!(
+        ("2024-12-07".."2024-12-15").contains(&earliest_sunset.as_str()),
+        "{}",
+        earliest_sunset
+    );
+    assert_eq!(date(&year.shortest_day), "2024-12-21");
+    assert_eq!(year.latest_sunrise.as_ref().unwrap().model, "physical");
+    assert!(analytics.to_string().contains(", from the physical fit\n"));
+}
+
+#[test]
+fn empty_datasets_have_no_years() {
+    let dataset = Dataset::parse("[]", String::from("empty"));
+
+    let analytics = Analytics::compute(&dataset);
+
+    assert!(analytics.years.is_empty());
+    assert_eq!(analytics.to_string(), "");
+}
@@ -0,0 +1,414 @@
+use std::io::{BufRead, BufReader, Write};
+use std::net::TcpListener;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use std::time::Duration;
+
+use libtad_rs::models::astronomy::{AstronomyEventClass, AstronomyObjectType};
+use libtad_rs::models::time::DateTime;
+use libtad_rs::service::astronomy::AstroEventRequest;
+use libtad_rs::service::time::TimeserviceRequest;
+
+use sun::cache::Cache;
+use sun::client::{Client, FetchError, RetryPolicy};
+use sun::place::{AstroInfo, PlaceQuery};
+
+/// A canned HTTP response: status, extra headers and body.
+type Reply = (u16, &'static str, &'static str);
+
+/// Serve `replies` in order, one per connection, and record the request
+/// lines seen. Returns the base URL and the request log.
+fn mock_server(replies: Vec<Reply>) -> (String, Arc<Mutex<Vec<String>>>) {
+    let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    let log = Arc::new(Mutex::new(Vec::new()));
+
+    let requests = Arc::clone(&log);
+    thread::spawn(move || {
+        for (status, headers, body) in replies {
+            let (mut stream, _) = listener.accept().unwrap();
+            let mut reader = BufReader::new(stream.try_clone().unwrap());
+
+            let mut request_line = String::new();
+            reader.read_line(&mut request_line).unwrap();
+            requests
+                .lock()
+                .unwrap()
+                .push(request_line.trim().to_string());
+            let mut line = String::new();
+            while reader.read_line(&mut line).unwrap() > 2 {
+                line.clear();
+            }
+
+            let response = format!(
+                "HTTP/1.1 {} X\r\nContent-Type: application/json\r\nContent-Length: {}\r\nConnection: close\r\n{}\r\n{}",
+                status,
+                body.len(),
+                headers,
+                body
+            );
+            stream.write_all(response.as_bytes()).unwrap();
+        }
+    });
+
+    (base_url, log)
+}
+
+fn client(base_url: &str, retries: u32) -> Client {
+    Client::new("key".to_string(), "secret".to_string())
+        .with_base_url(base_url)
+        .with_retry(RetryPolicy {
+            retries,
+            base_delay: Duration::from_millis(10),
+            max_delay: Duration::from_millis(50),
+        })
+}
+
+fn request() -> AstroEventRequest {
+    AstroEventRequest::new()
+        .with_object(AstronomyObjectType::Sun)
+        .with_placeid("seattle")
+        .set_startdt(DateTime {
+            year: 2024,
+            month: 1,
+            day: 1,
+            hour: 0,
+            minute: 0,
+            second: 0,
+        })
+        .with_type(AstronomyEventClass::SetRise)
+}
+
+const EMPTY: &str = r#"{"locations":[]}"#;
+
+#[tokio::test]
+async fn signs_the_request() {
+    let (base_url, log) = mock_server(vec![(200, "", EMPTY)]);
+
+    let response = client(&base_url, 0)
+        .get_astro_events(&request())
+        .await
+        .unwrap();
+    assert!(response.locations.is_empty());
+
+    let log = log.lock().unwrap();
+    assert!(log[0].starts_with("GET /astronomy?"), "{}", log[0]);
+    for param in [
+        "placeid=seattle",
+        "accesskey=key",
+        "signature=",
+        "timestamp=",
+        "version=3",
+    ] {
+        assert!(log[0].contains(param), "{} missing from {}", param, log[0]);
+    }
+}
+
+#[tokio::test]
+async fn retries_server_errors() {
+    let (base_url, log) = mock_server(vec![(503, "", ""), (502, "", ""), (200, "", EMPTY)]);
+
+    let response = client(&base_url, 3).get_astro_events(&request()).await;
+
+    assert!(response.is_ok());
+    assert_eq!(log.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn honours_retry_after() {
+    let (base_url, log) = mock_server(vec![(429, "Retry-After: 1\r\n", ""), (200, "", EMPTY)]);
+
+    let started = std::time::Instant::now();
+    let response = client(&base_url, 1).get_astro_events(&request()).await;
+
+    assert!(response.is_ok());
+    assert_eq!(log.lock().unwrap().len(), 2);
+    assert!(started.elapsed() >= Duration::from_secs(1));
+}
+
+#[tokio::test]
+async fn rate_limit_past_retries_is_quota() {
+    let (base_url, _) = mock_server(vec![(429, "", ""), (429, "", "")]);
+
+    let error = client(&base_url, 1)
+        .get_astro_events(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, FetchError::Quota(_)), "{:?}", error);
+    assert_eq!(error.exit_code(), 4);
+}
+
+#[tokio::test]
+async fn payment_required_is_quota_without_retries() {
+    let (base_url, log) = mock_server(vec![(402, "", "")]);
+
+    let error = client(&base_url, 3)
+        .get_astro_events(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, FetchError::Quota(_)), "{:?}", error);
+    assert_eq!(log.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn concurrent_requests_share_the_rate() {
+    let (base_url, log) = mock_server(vec![(200, "", EMPTY); 3]);
+    let client = Arc::new(client(&base_url, 0).with_rate(10.0));
+
+    let started = std::time::Instant::now();
+    let fetches: Vec<_> = (0..3)
+        .map(|_| {
+            let client = Arc::clone(&client);
+            tokio::spawn(async move { client.get_astro_events(&request()).await })
+        })
+        .collect();
+    for fetch in fetches {
+        fetch.await.unwrap().unwrap();
+    }
+
+    // the third waits two intervals of 100 ms
+    assert!(started.elapsed() >= Duration::from_millis(200));
+    assert_eq!(log.lock().unwrap().len(), 3);
+}
+
+#[tokio::test]
+async fn auth_failures_are_not_retried() {
+    let (base_url, log) = mock_server(vec![(403, "", r#"{"errors":["Invalid signature"]}"#)]);
+
+    let error = client(&base_url, 3)
+        .get_astro_events(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, FetchError::Auth(_)), "{:?}", error);
+    assert_eq!(error.exit_code(), 3);
+    assert_eq!(log.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn classifies_api_errors() {
+    let cases = [
+        (r#"{"errors":["Unknown place: atlantis"]}"#, 5),
+        (r#"{"errors":["No credits left on account"]}"#, 4),
+        (r#"{"errors":["Invalid accesskey"]}"#, 3),
+        (r#"{"errors":["Invalid value for parameter types"]}"#, 7),
+        (r#"{"errors":["Rate limit exceeded"]}"#, 4),
+        // not a quota error for mentioning the word
+        (r#"{"errors":["Invalid value for parameter limit"]}"#, 7),
+    ];
+
+    for (body, code) in cases {
+        let (base_url, _) = mock_server(vec![(200, "", body)]);
+        let error = client(&base_url, 3)
+            .get_astro_events(&request())
+            .await
+            .unwrap_err();
+        assert_eq!(error.exit_code(), code, "{}: {:?}", body, error);
+    }
+}
+
+#[tokio::test]
+async fn unreachable_server_is_network_error() {
+    // grab a free port and close it again
+    let base_url = {
+        let listener = TcpListener::bind("127.0.0.1:0").unwrap();
+        format!("http://{}", listener.local_addr().unwrap())
+    };
+
+    let error = client(&base_url, 2)
+        .get_astro_events(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, FetchError::Network(_)), "{:?}", error);
+    assert_eq!(error.exit_code(), 6);
+}
+
+#[tokio::test]
+async fn malformed_response_is_decode_error() {
+    let (base_url, _) = mock_server(vec![(200, "", r#"{"locations":"#)]);
+
+    let error = client(&base_url, 0)
+        .get_astro_events(&request())
+        .await
+        .unwrap_err();
+
+    assert_eq!(error.exit_code(), 8);
+}
+
+#[tokio::test]
+async fn looks_places_up_by_name() {
+    let places = r#"{"places":[{"id":1234,"urlid":"usa/seattle","geo":{"name":"Seattle","state":"Washington","country":{"id":"us","name":"United States"},"latitude":47.606,"longitude":-122.332}}]}"#;
+    let (base_url, log) = mock_server(vec![(200, "", places)]);
+
+    let query = PlaceQuery::parse("Seattle, WA");
+    assert_eq!(query, PlaceQuery::Name("Seattle, WA".to_string()));
+    let response = client(&base_url, 0)
+        .get_places(&query.places_request())
+        .await
+        .unwrap();
+
+    let log = log.lock().unwrap();
+    assert!(log[0].starts_with("GET /places?"), "{}", log[0]);
+    assert!(log[0].contains("query=Seattle%2C+WA"), "{}", log[0]);
+    assert!(log[0].contains("geo=1"), "{}", log[0]);
+
+    let info = AstroInfo::from(&response.places[0].geo);
+    assert_eq!(
+        info.to_string(),
+        "Seattle, Washington, United States (47.61°N 122.33°W)"
+    );
+}
+
+#[tokio::test]
+async fn reads_the_time_zone() {
+    // fetched in summer: the zone is on daylight saving, its base offset not
+    let time = r#"{"locations":[{"id":"1234","matchparam":"usa/seattle","geo":{"name":"Seattle","state":"Washington","country":{"id":"us","name":"United States"},"latitude":47.606,"longitude":-122.332},"time":{"iso":"2024-07-01T12:00:00-07:00","datetime":{"year":2024,"month":7,"day":1,"hour":12,"minute":0,"second":0},"timezone":{"offset":"-07:00","zoneabb":"PDT","zonename":"Pacific Daylight Time","zoneoffset":-28800,"zonedst":3600,"zonetotaloffset":-25200}}}]}"#;
+    let (base_url, log) = mock_server(vec![(200, "", time)]);
+
+    let response = client(&base_url, 0)
+        .get_current_time(
+            &TimeserviceRequest::new()
+                .set_placeid("usa/seattle")
+                .set_tz(true),
+        )
+        .await
+        .unwrap();
+
+    let log = log.lock().unwrap();
+    assert!(log[0].starts_with("GET /timeservice?"), "{}", log[0]);
+    let timezone = response.locations[0]
+        .time
+        .as_ref()
+        .unwrap()
+        .timezone
+        .as_ref()
+        .unwrap();
+    assert_eq!(timezone.zoneoffset, -8 * 3600);
+}
+
+#[test]
+fn shows_the_time_zone() {
+    let mut info: AstroInfo = serde_json::from_str(
+        r#"{"country":"United States","state":"Washington","city":"Seattle","lat":47.61,"lon":-122.33,"lat_dir":"N","lon_dir":"W","utc_offset":-8.0,"timezone":"Pacific Standard Time"}"#,
+    )
+    .unwrap();
+    assert_eq!(
+        info.to_string(),
+        "Seattle, Washington, United States (47.61°N 122.33°W, UTC-08:00, Pacific Standard Time)"
+    );
+    info.timezone = None;
+    assert!(!serde_json::to_string(&info).unwrap().contains("timezone"));
+}
+
+#[test]
+fn tells_place_ids_from_names() {
+    for id in ["179", "usa/seattle", "+47.6-122.3"] {
+        assert_eq!(PlaceQuery::parse(id), PlaceQuery::Id(id.to_string()));
+    }
+    assert_eq!(PlaceQuery::coords_id(47.6, -122.3), "+47.6-122.3");
+}
+
+/// A fresh, empty cache directory for one test.
+fn cache_dir(test: &str) -> std::path::PathBuf {
+    let dir = std::env::temp_dir().join(format!("sun-cache-{}-{}", std::process::id(), test));
+    let _ = std::fs::remove_dir_all(&dir);
+    dir
+}
+
+#[tokio::test]
+async fn cached_responses_skip_the_api() {
+    let dir = cache_dir("hit");
+    let (base_url, log) = mock_server(vec![(200, "", EMPTY)]);
+    let cached = || client(&base_url, 0).with_cache(Cache::new(&dir, Duration::from_secs(3600)));
+
+    cached().get_astro_events(&request()).await.unwrap();
+    cached().get_astro_events(&request()).await.unwrap();
+
+    assert_eq!(log.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn cached_responses_belong_to_their_endpoint() {
+    let dir = cache_dir("endpoint");
+    let (first_url, first_log) = mock_server(vec![(200, "", EMPTY)]);
+    let (second_url, second_log) = mock_server(vec![(200, "", EMPTY)]);
+    let cached = |base_url: &str| {
+        client(base_url, 0).with_cache(Cache::new(&dir, Duration::from_secs(3600)))
+    };
+
+    cached(&first_url)
+        .get_astro_events(&request())
+        .await
+        .unwrap();
+    cached(&second_url)
+        .get_astro_events(&request())
+        .await
+        .unwrap();
+
+    assert_eq!(first_log.lock().unwrap().len(), 1);
+    assert_eq!(second_log.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn expired_entries_are_refetched() {
+    let dir = cache_dir("expired");
+    let (base_url, log) = mock_server(vec![(200, "", EMPTY), (200, "", EMPTY)]);
+    let cached = || client(&base_url, 0).with_cache(Cache::new(&dir, Duration::ZERO));
+
+    cached().get_astro_events(&request()).await.unwrap();
+    std::thread::sleep(Duration::from_millis(10));
+    cached().get_astro_events(&request()).await.unwrap();
+
+    assert_eq!(log.lock().unwrap().len(), 2);
+}
+
+#[tokio::test]
+async fn offline_uses_stale_entries_without_credentials() {
+    let dir = cache_dir("offline");
+    let (base_url, log) = mock_server(vec![(200, "", EMPTY)]);
+    client(&base_url, 0)
+        .with_cache(Cache::new(&dir, Duration::ZERO))
+        .get_astro_events(&request())
+        .await
+        .unwrap();
+
+    let offline = Client::new(String::new(), String::new())
+        .with_base_url(&base_url)
+        .with_cache(Cache::new(&dir, Duration::ZERO))
+        .with_offline(true);
+
+    assert!(offline.get_astro_events(&request()).await.is_ok());
+    assert_eq!(log.lock().unwrap().len(), 1);
+}
+
+#[tokio::test]
+async fn offline_cache_miss_fails() {
+    let dir = cache_dir("miss");
+
+    let error = Client::new(String::new(), String::new())
+        .with_cache(Cache::new(&dir, Duration::from_secs(3600)))
+        .with_offline(true)
+        .get_astro_events(&request())
+        .await
+        .unwrap_err();
+
+    assert!(matches!(error, FetchError::Offline(_)), "{:?}", error);
+    assert_eq!(error.exit_code(), 9);
+}
+
+#[tokio::test]
+async fn failures_are_not_cached() {
+    let dir = cache_dir("failure");
+    let (base_url, log) = mock_server(vec![
+        (200, "", r#"{"errors":["Unknown place: atlantis"]}"#),
+        (200, "", EMPTY),
+    ]);
+    let cached = || client(&base_url, 0).with_cache(Cache::new(&dir, Duration::from_secs(3600)));
+
+    assert!(cached().get_astro_events(&request()).await.is_err());
+    assert!(cached().get_astro_events(&request()).await.is_ok());
+    assert_eq!(log.lock().unwrap().len(), 2);
+}
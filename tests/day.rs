@@ -0,0 +1,150 @@
+use sun::day::{day_of_year, days_in_month, Dataset, Quantity, Resample};
+use sun::fit::{Fitment, SineFitment};
+
+#[test]
+fn day_of_year_counts_leap_days() {
+    assert_eq!(day_of_year(2023, 3, 1), 60);
+    assert_eq!(day_of_year(2024, 3, 1), 61);
+    assert_eq!(day_of_year(2024, 12, 31), 366);
+    assert_eq!(day_of_year(1900, 12, 31), 365);
+    assert_eq!(day_of_year(2000, 12, 31), 366);
+    assert_eq!(days_in_month(2024, 2), 29);
+    assert_eq!(days_in_month(2100, 2), 28);
+    assert_eq!(days_in_month(2024, 13), 0);
+}
+
+#[test]
+fn days_continue_across_years() {
+    let dataset = Dataset::parse(
+        r#"[
+            {"y": 2025, "m": 1, "d": 2, "yday": 2, "srise": "7:50", "sset": "16:30"},
+            {"y": 2024, "m": 12, "d": 31, "yday": 365, "srise": "7:55", "sset": "16:25"},
+            {"y": 2024, "m": 3, "d": 1, "srise": "6:50", "sset": "17:50"}
+        ]"#,
+        String::from("test"),
+    );
+
+    let days: Vec<(u64, u64)> = dataset
+        .days
+        .iter()
+        .map(|day| (day.yday, dataset.day_number(day)))
+        .collect();
+    assert_eq!(days, [(61, 61), (366, 366), (2, 368)]);
+    assert_eq!(dataset.axis_days(), 731);
+}
+
+#[test]
+fn resamples_to_every_day() {
+    let days: Vec<String> = (1..=12)
+        .map(|m| {
+            let hours = 12.0 + 3.0 * ((m as f64 - 3.7) / 6.0 * std::f64::consts::PI).sin();
+            format!(
+                r#"{{"y": 2023, "m": {}, "d": 15, "srise": {}, "sset": {}}}"#,
+                m,
+                12.0 - hours / 2.0,
+                12.0 + hours / 2.0
+            )
+        })
+        .collect();
+
+    let dataset = Dataset::parse(&format!("[{}]", days.join(",")), String::from("test"));
+    let rows = dataset.resampled(Resample::Spline, false);
+
+    assert_eq!(rows.len(), 365);
+    assert_eq!(
+        rows.iter()
+            .filter(|row| row.interpolated == Some(false))
+            .count(),
+        12
+    );
+    for (number, row) in (1..).zip(&rows) {
+        assert_eq!(row.yday, number);
+        let srise = row.srise.as_ref().unwrap().get_num();
+        let sset = row.sset.as_ref().unwrap().get_num();
+        assert!(srise < 12.0 && sset > 12.0, "day {}", number);
+    }
+    assert_eq!(
+        rows[14].srise.as_ref().unwrap().get_num(),
+        dataset.days[0].srise.as_ref().unwrap().get_num()
+    );
+}
+
+#[test]
+fn resampling_repeats_every_year() {
+    // a year of samples, and one more the next January
+    let mut days: Vec<String> = (1..=12)
+        .map(|m| {
+            let hours = 12.0 + 3.0 * ((m as f64 - 3.7) / 6.0 * std::f64::consts::PI).sin();
+            format!(
+                r#"{{"y": 2023, "m": {}, "d": 15, "srise": 6.0, "sset": {}}}"#,
+                m,
+                6.0 + hours
+            )
+        })
+        .collect();
+    days.push(String::from(
+        r#"{"y": 2024, "m": 1, "d": 15, "srise": 6.0, "sset": 15.1}"#,
+    ));
+    let dataset = Dataset::parse(&format!("[{}]", days.join(",")), String::from("test"));
+
+    let rows = dataset.resampled(Resample::Spline, false);
+
+    assert_eq!(rows.len(), 731);
+    let sset = |number: usize| rows[number - 1].sset.as_ref().unwrap().get_num();
+    // mid July 2024 follows mid July 2023, not a line back to January
+    assert!((sset(196 + 366) - sset(196)).abs() < 0.05);
+}
+
+#[test]
+fn excludes_flagged_outliers() {
+    let mut dataset = Dataset::parse(
+        r#"[
+            {"y": 2023, "m": 1, "d": 15, "srise": 7.8, "sset": 16.7},
+            {"y": 2023, "m": 2, "d": 15, "srise": 19.3, "sset": 17.4},
+            {"y": 2023, "m": 3, "d": 15, "srise": 7.2, "sset": 19.2}
+        ]"#,
+        String::from("test"),
+    );
+    dataset.outliers.push((Quantity::Sunrise, 46));
+
+    let rows = dataset.xdays(true);
+    assert!(rows[1].srise.is_none());
+    assert!(rows[1].sset.is_some());
+    assert!(dataset.xdays(false)[1].srise.is_some());
+
+    let resampled = dataset.resampled(Resample::Spline, true);
+    assert!(resampled[45].srise.is_none());
+    assert_eq!(resampled[45].interpolated, Some(false));
+}
+
+#[test]
+fn change_rates_follow_the_fit() {
+    let mut dataset = Dataset::parse(
+        r#"[
+            {"y": 2024, "m": 3, "d": 20, "daylen": 12.2},
+            {"y": 2024, "m": 6, "d": 20, "daylen": 15.9}
+        ]"#,
+        String::from("test"),
+    );
+    let fitment = SineFitment {
+        period_mul_2pi: 1.0,
+        a: 3.7,
+        b: 2.0 * std::f64::consts::PI / 365.2422,
+        c: -80.0,
+        d: 12.2,
+    };
+    dataset
+        .fits
+        .push((Quantity::DayLength, Fitment::Sine(fitment.clone())));
+
+    let rate = dataset.change_rate(Quantity::DayLength, 80).unwrap();
+    assert!((rate - (fitment.eval(81.0) - fitment.eval(80.0)) * 60.0).abs() < 1e-9);
+    assert!(rate > 3.5);
+    assert!(dataset.change_rate(Quantity::Sunrise, 80).is_none());
+
+    let rows = dataset.transformed(None, false, true);
+    assert_eq!(rows[0].daylen_change, Some(rate));
+    assert!(rows[0].srise_change.is_none());
+    let rows = dataset.transformed(None, false, false);
+    assert!(rows[1].daylen_change.is_none());
+}
@@ -0,0 +1,72 @@
+use sun::day::{day_of_year, Dataset, Day, NumOrStr};
+use sun::eot::EquationOfTime;
+use sun::solar;
+
+/// Seattle's solar noons every week of 2024 on its clocks: an hour later
+/// from April to October, and one mistyped an hour late in July.
+fn seattle() -> Dataset {
+    let days: Vec<Day> = (1..=12u64)
+        .flat_map(|m| [1, 8, 15, 22].map(|d| (m, d)))
+        .map(|(m, d)| {
+            let sun = solar::solar_day(2024, m, d, 47.61, -122.33, -8.0);
+            let dst = if (4..=10).contains(&m) { 1.0 } else { 0.0 };
+            let stray = if (m, d) == (7, 15) { 1.0 } else { 0.0 };
+            Day {
+                y: 2024,
+                m,
+                d,
+                yday: day_of_year(2024, m, d),
+                solnoon: Some(NumOrStr::Num(sun.noon + dst + stray)),
+                ..Default::default()
+            }
+        })
+        .collect();
+    Dataset::from_days(String::from("Seattle"), days)
+}
+
+#[test]
+fn equation_of_time_from_the_solar_noons() {
+    let dataset = seattle();
+
+    let equation = EquationOfTime::derive(&dataset).unwrap();
+
+    assert_eq!(equation.dst_days, 27);
+    assert_eq!(equation.stray_days, 1);
+    assert_eq!(equation.samples.len(), 47);
+    assert!(equation.calculator_rms < 0.5, "{}", equation);
+    assert!(
+        (equation.longitude(-8.0) + 122.33).abs() < 0.2,
+        "{}",
+        equation
+    );
+    // least in mid February, greatest at the start of November
+    assert!(equation.least.0.starts_with("2024-02"), "{}", equation);
+    assert!((equation.least.1 + 14.2).abs() < 0.5, "{}", equation);
+    assert!(
+        equation.greatest.0.starts_with("2024-10-3") || equation.greatest.0.starts_with("2024-11"),
+        "{}",
+        equation
+    );
+    assert!((equation.greatest.1 - 16.4).abs() < 0.5, "{}", equation);
+    assert!(equation.analemma(&dataset).is_empty());
+}
+
+#[test]
+fn analemma_pairs_noon_altitudes() {
+    let mut dataset = seattle();
+    dataset.fill_angles(47.61, -122.33);
+
+    let equation = EquationOfTime::derive(&dataset).unwrap();
+    let analemma = equation.analemma(&dataset);
+
+    assert_eq!(analemma.len(), equation.samples.len());
+    let (low, high) = analemma
+        .iter()
+        .fold((f64::MAX, f64::MIN), |(low, high), &(_, altitude, _)| {
+            (low.min(altitude), high.max(altitude))
+        });
+    // 90° less the latitude, give or take the obliquity
+    assert!((low - (90.0 - 47.61 - 23.44)).abs() < 1.0, "{}", low);
+    assert!((high - (90.0 - 47.61 + 23.44)).abs() < 1.0, "{}", high);
+    assert!(analemma.windows(2).all(|pair| pair[0].2 < pair[1].2));
+}
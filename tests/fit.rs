@@ -0,0 +1,425 @@
+use sun::day::{day_of_year, Dataset, Day, NumOrStr, Quantity};
+use sun::fit::{
+    Candidate, CrossValidation, Fitment, FourierFitment, Joint, JointFitment, Loss, Model,
+    ModelChoice, PeriodicSpline, PhysicalFit, RobustFit, SineFitment, SineFitter, SineIntervals,
+    SplineFitment,
+};
+use sun::place::AstroInfo;
+use sun::solar;
+
+/// Sunrise/sunset/noon every `step` days of 2024 from the solar calculator,
+/// an hour later from April to October as if on daylight saving time.
+fn synthetic_days(latitude: f64, longitude: f64, utc_offset: f64, step: usize) -> Vec<Day> {
+    (1..=12u64)
+        .flat_map(|m| (1..=28u64).map(move |d| (m, d)))
+        .step_by(step)
+        .map(|(m, d)| {
+            let sun = solar::solar_day(2024, m, d, latitude, longitude, utc_offset);
+            let dst = if (4..=10).contains(&m) { 1.0 } else { 0.0 };
+            let (srise, sset) = (sun.sunrise.unwrap() + dst, sun.sunset.unwrap() + dst);
+            Day {
+                y: 2024,
+                m,
+                d,
+                yday: day_of_year(2024, m, d),
+                srise: Some(NumOrStr::Num(srise)),
+                sset: Some(NumOrStr::Num(sset)),
+                solnoon: Some(NumOrStr::Num(sun.noon + dst)),
+                daylen: Some(NumOrStr::Num(sset - srise)),
+                ..Default::default()
+            }
+        })
+        .collect()
+}
+
+#[test]
+fn physical_fit_recovers_the_position() {
+    for (latitude, longitude, utc_offset) in [(47.61, -122.33, -8.0), (-33.87, 151.21, 10.0)] {
+        let days = synthetic_days(latitude, longitude, utc_offset, 10);
+
+        let fit = PhysicalFit::fit(&days, Some(utc_offset)).unwrap();
+
+        assert!((fit.latitude - latitude).abs() < 0.2, "{}", fit);
+        assert!(
+            (fit.longitude().unwrap() - longitude).abs() < 0.2,
+            "{}",
+            fit
+        );
+        assert_eq!(
+            fit.dst_days(),
+            days.iter().filter(|day| (4..=10).contains(&day.m)).count()
+        );
+        assert!(fit.rms_minutes < 1.0, "{}", fit);
+    }
+}
+
+#[test]
+fn physical_fit_needs_three_days() {
+    let days = synthetic_days(47.61, -122.33, -8.0, 150);
+
+    assert_eq!(days.len(), 3);
+    assert!(PhysicalFit::fit(&days[..2], None).is_none());
+}
+
+#[test]
+fn confidence_region_tells_cities_apart() {
+    let days = synthetic_days(32.22, -110.97, -7.0, 10);
+
+    let fit = PhysicalFit::fit(&days, Some(-7.0)).unwrap();
+
+    assert!(fit.contains(32.22, -110.97, -7.0), "{}", fit);
+    assert!(!fit.contains(47.61, -122.33, -8.0), "{}", fit);
+    assert!(!fit.contains(33.45, -112.07, -7.0), "Phoenix: {}", fit);
+}
+
+#[test]
+fn periodic_spline_passes_through_the_samples() {
+    let curve = |x: f64| 12.0 + 3.0 * (2.0 * std::f64::consts::PI * x / 365.0).sin();
+    let samples: Vec<(f64, f64)> = [3, 21, 50, 90, 130, 160, 200, 240, 280, 300, 330, 350]
+        .into_iter()
+        .map(|x| (x as f64, curve(x as f64)))
+        .collect();
+
+    let spline = PeriodicSpline::interpolate(&samples, 365.0).unwrap();
+
+    for &(x, y) in &samples {
+        assert!((spline.eval(x) - y).abs() < 1e-9);
+        assert!((spline.eval(x + 365.0) - y).abs() < 1e-9);
+    }
+    for x in 0..365 {
+        let x = x as f64;
+        assert!((spline.eval(x) - curve(x)).abs() < 0.01, "day {}", x);
+    }
+    assert!(PeriodicSpline::interpolate(&samples[..2], 365.0).is_none());
+}
+
+#[test]
+fn smoothing_spline_follows_the_curve_through_noise() {
+    let curve = |x: f64| 12.0 + 3.0 * (2.0 * std::f64::consts::PI * x / 365.2422).sin();
+    // deterministic ±3 minute jitter
+    let noise = |i: usize| [0.05, -0.04, 0.02, -0.05, 0.03, -0.01][i % 6];
+    let samples: Vec<(f64, f64)> = (0..60)
+        .map(|i| {
+            let x = (i * 6 + 2) as f64;
+            (x, curve(x) + noise(i))
+        })
+        .collect();
+
+    let fitment = SplineFitment::fit(&samples, 365.2422).unwrap();
+
+    let error = (0..365)
+        .map(|x| (fitment.eval(x as f64) - curve(x as f64)).abs())
+        .fold(0.0, f64::max);
+    assert!(error < 0.03, "max error {} h, {}", error, fitment);
+    assert!(
+        fitment.cv_rms > 0.02 && fitment.cv_rms < 0.08,
+        "{}",
+        fitment
+    );
+    assert!(fitment.lambda > 1.0, "{}", fitment);
+}
+
+#[test]
+fn smoothing_spline_handles_a_sample_every_day() {
+    let curve = |x: f64| 7.0 + 1.5 * (2.0 * std::f64::consts::PI * (x + 80.0) / 365.2422).sin();
+    let noise = |i: usize| [0.01, -0.008, 0.004, -0.012, 0.006][i % 5];
+    let samples: Vec<(f64, f64)> = (1..=366)
+        .map(|day| (day as f64, curve(day as f64) + noise(day)))
+        .collect();
+
+    let started = std::time::Instant::now();
+    let fitment = SplineFitment::fit(&samples, 365.2422).unwrap();
+
+    // a dense solve per candidate λ took minutes here
+    assert!(started.elapsed().as_secs() < 30, "{:?}", started.elapsed());
+    let error = (1..=366)
+        .map(|x| (fitment.eval(x as f64) - curve(x as f64)).abs())
+        .fold(0.0, f64::max);
+    assert!(error < 0.01, "max error {} h, {}", error, fitment);
+}
+
+#[test]
+fn models_are_chosen_per_quantity() {
+    let choices: Vec<ModelChoice> = ["spline", "solnoon=physical", "solar-noon=sine"]
+        .into_iter()
+        .map(|choice| ModelChoice::parse(choice).unwrap())
+        .collect();
+
+    assert_eq!(
+        ModelChoice::models_for(&choices, Quantity::Sunrise),
+        [Model::Spline]
+    );
+    assert_eq!(
+        ModelChoice::models_for(&choices, Quantity::SolarNoon),
+        [Model::Physical, Model::Sine]
+    );
+    assert_eq!(
+        ModelChoice::models_for(
+            &[ModelChoice::parse("physical").unwrap()],
+            Quantity::CivilDawn
+        ),
+        [Model::Sine]
+    );
+    assert!(ModelChoice::parse("noon=spline").is_err());
+    assert!(ModelChoice::parse("cubic").is_err());
+}
+
+#[test]
+fn robust_fit_flags_a_mistyped_time() {
+    let curve = |x: f64| 7.0 + 1.5 * (2.0 * std::f64::consts::PI * (x + 80.0) / 365.2422).sin();
+    let noise = |i: usize| [0.02, -0.01, 0.015, -0.02, 0.005][i % 5];
+    let mut samples: Vec<(f64, u64)> = (0..40)
+        .map(|i| {
+            let day = i * 9 + 3;
+            (curve(day as f64) + noise(i as usize), day)
+        })
+        .collect();
+    // PM for AM on one day
+    samples[12].0 += 12.0;
+
+    for loss in [Loss::Huber, Loss::Tukey] {
+        let fit = RobustFit::fit(&samples, 1.0, loss).unwrap();
+
+        assert_eq!(fit.outliers, [samples[12]], "{:?}", loss);
+        let error = (1..=365)
+            .map(|x| (fit.fitment.eval(x as f64) - curve(x as f64)).abs())
+            .fold(0.0, f64::max);
+        assert!(error < 0.05, "{:?}: max error {} h", loss, error);
+    }
+    assert!(RobustFit::fit(&samples[..2], 1.0, Loss::Tukey).is_none());
+}
+
+#[test]
+fn robust_fit_takes_daylight_saving_time_out() {
+    let dataset = Dataset::from_days(
+        String::from("Seattle"),
+        synthetic_days(47.61, -122.33, -8.0, 3),
+    );
+    let clock = dataset.series(Quantity::Sunrise);
+    let standard = dataset.standard_time(Quantity::Sunrise);
+
+    for (&(clock, day), &(standard, _)) in clock.iter().zip(&standard) {
+        let (_, m, _) = dataset.date(day);
+        let dst = if (4..=10).contains(&m) { 1.0 } else { 0.0 };
+        assert!((clock - standard - dst).abs() < 1e-9, "day {}", day);
+    }
+    assert_eq!(
+        dataset.standard_time(Quantity::DayLength),
+        dataset.series(Quantity::DayLength)
+    );
+    let fit = RobustFit::fit(&standard, 1.0, Loss::Tukey).unwrap();
+    assert!(fit.outliers.is_empty(), "{:?}", fit.outliers);
+}
+
+#[test]
+fn intervals_cover_the_true_curve() {
+    let truth = SineFitment {
+        period_mul_2pi: 1.0,
+        a: 1.5,
+        b: 2.0 * std::f64::consts::PI / 365.2422,
+        c: 80.0,
+        d: 7.0,
+    };
+    // deterministic noise, SD about 0.1 h
+    let mut state = 12345u64;
+    let mut noise = || {
+        state = state
+            .wrapping_mul(6364136223846793005)
+            .wrapping_add(1442695040888963407);
+        ((state >> 11) as f64 / (1u64 << 53) as f64 - 0.5) * 0.35
+    };
+    let samples: Vec<(f64, u64)> = (0..120)
+        .map(|i| {
+            let day = i * 3 + 1;
+            (truth.eval(day as f64) + noise(), day)
+        })
+        .collect();
+
+    // Huber weights leave this noise alone: least squares
+    let fit = RobustFit::fit(&samples, 1.0, Loss::Huber).unwrap().fitment;
+    let intervals = SineIntervals::estimate(&fit, &samples).unwrap();
+
+    assert!((intervals.sd - 0.1).abs() < 0.02, "{}", intervals);
+    assert!(
+        (fit.a - truth.a).abs() < 3.0 * intervals.a_se,
+        "{}",
+        intervals
+    );
+    assert!(
+        (fit.c - truth.c).abs() < 3.0 * intervals.c_se,
+        "{}",
+        intervals
+    );
+    assert!(
+        (fit.d - truth.d).abs() < 3.0 * intervals.d_se,
+        "{}",
+        intervals
+    );
+    for x in [1.0, 100.0, 200.0, 300.0] {
+        let error = (fit.eval(x) - truth.eval(x)).abs();
+        assert!(error < intervals.confidence(x), "day {}: {}", x, error);
+        assert!(intervals.prediction(x) > 1.96 * intervals.sd);
+    }
+    assert!(SineIntervals::estimate(&fit, &samples[..3]).is_none());
+}
+
+#[test]
+fn intervals_hold_the_parameters_shown() {
+    let curve = |x: f64| 7.0 + 1.5 * (2.0 * std::f64::consts::PI * (x + 80.0) / 365.2422).sin();
+    let noise = |i: usize| [0.12, -0.2, 0.05, -0.1, 0.25, -0.15][i % 6];
+    let samples: Vec<(f64, u64)> = (0..40)
+        .map(|i| {
+            let day = i * 9 + 3;
+            (curve(day as f64) + noise(i as usize), day)
+        })
+        .collect();
+
+    let extremes = SineFitter::fit_quietly(&samples, 1.0).unwrap();
+    let robust = RobustFit::fit(&samples, 1.0, Loss::Tukey).unwrap().fitment;
+    for shown in [extremes, robust] {
+        let intervals = SineIntervals::estimate(&shown, &samples).unwrap();
+
+        let fit = &intervals.fitment;
+        assert_eq!((fit.a, fit.c, fit.d), (shown.a, shown.c, shown.d));
+        let within = |value: f64, shown: f64, se: f64| (value - shown).abs() <= 1.96 * se;
+        assert!(within(fit.a, shown.a, intervals.a_se), "{}", intervals);
+        assert!(within(fit.c, shown.c, intervals.c_se), "{}", intervals);
+        assert!(within(fit.d, shown.d, intervals.d_se), "{}", intervals);
+        let printed = intervals.to_string();
+        assert!(
+            printed.starts_with(&format!("A = {:.3} ± ", shown.a)),
+            "{}",
+            printed
+        );
+        assert!(intervals.confidence(100.0) > 0.0);
+    }
+}
+
+#[test]
+fn fourier_series_fits_its_harmonics() {
+    let curve = |x: f64| {
+        let angle = 2.0 * std::f64::consts::PI * x / 365.2422;
+        12.2 + 0.1 * angle.sin() - 0.12 * angle.cos() + 0.16 * (2.0 * angle).sin()
+    };
+    let points: Vec<(f64, f64)> = (0..20)
+        .map(|i| {
+            let x = (i * 18 + 5) as f64;
+            (x, curve(x))
+        })
+        .collect();
+
+    let fitment = FourierFitment::fit(&points, 365.2422, 2).unwrap();
+
+    assert_eq!(fitment.harmonics(), 2);
+    assert!(fitment.rms < 1e-9, "{}", fitment);
+    assert!((fitment.eval(200.0) - curve(200.0)).abs() < 1e-9);
+    assert!(FourierFitment::fit(&points[..5], 365.2422, 2).is_none());
+}
+
+#[test]
+fn cross_validation_prefers_the_generalising_model() {
+    // a noon with the equation of time's two swings a year
+    let noon = |x: f64| {
+        let angle = 2.0 * std::f64::consts::PI * x / 365.2422;
+        12.2 + 0.12 * angle.sin() - 0.05 * angle.cos() + 0.16 * (2.0 * angle).sin()
+    };
+    let days: Vec<String> = (1..=12u64)
+        .flat_map(|m| [3u64, 17].map(move |d| (m, d)))
+        .map(|(m, d)| {
+            let x = day_of_year(2023, m, d) as f64;
+            format!(
+                r#"{{"y": 2023, "m": {}, "d": {}, "solnoon": {}}}"#,
+                m,
+                d,
+                noon(x)
+            )
+        })
+        .collect();
+    let dataset = Dataset::parse(&format!("[{}]", days.join(",")), String::from("test"));
+
+    let validation = CrossValidation::run(&dataset, Quantity::SolarNoon, None).unwrap();
+
+    assert_eq!(validation.folds, 24);
+    assert!(
+        matches!(validation.best(), Some(Candidate::Fourier(2..))),
+        "{}",
+        validation
+    );
+    let rms = |candidate| {
+        validation
+            .scores
+            .iter()
+            .find(|(c, _)| *c == candidate)
+            .unwrap()
+            .1
+    };
+    assert!(rms(Candidate::Fourier(2)) < 1e-6, "{}", validation);
+    assert!(rms(Candidate::Fourier(1)) > 0.05, "{}", validation);
+
+    let validation = CrossValidation::run(&dataset, Quantity::SolarNoon, Some(4)).unwrap();
+    assert_eq!(validation.folds, 4);
+    assert!(validation
+        .to_string()
+        .starts_with("Cross-validation Solar Noon (4-fold, 24 days): fourier-"));
+}
+
+#[test]
+fn cross_validation_places_the_sun_with_the_location_utc_offset() {
+    let days = synthetic_days(47.61, -122.33, -8.0, 4);
+    let mut dataset = Dataset::from_days(String::from("test"), days);
+    let physical = |dataset: &Dataset| {
+        let validation = CrossValidation::run(dataset, Quantity::DayLength, None).unwrap();
+        validation
+            .scores
+            .iter()
+            .find(|(candidate, _)| *candidate == Candidate::Physical)
+            .unwrap()
+            .1
+    };
+    let unplaced = physical(&dataset);
+
+    dataset.location = Some(AstroInfo {
+        country: String::from("United States"),
+        state: String::from("Washington"),
+        city: String::from("Seattle"),
+        lat: 47.61,
+        lon: -122.33,
+        lat_dir: String::from("N"),
+        lon_dir: String::from("W"),
+        utc_offset: Some(-8.0),
+        timezone: None,
+    });
+    let placed = physical(&dataset);
+
+    assert!(placed < 0.2 / 60.0, "{} min", placed * 60.0);
+    assert!(placed < unplaced / 2.0, "{} min", unplaced * 60.0);
+}
+
+#[test]
+fn joint_fits_keep_the_identities() {
+    let sine = |a: f64, c: f64, d: f64, period_mul_2pi: f64| {
+        Fitment::Sine(SineFitment {
+            period_mul_2pi,
+            a,
+            b: period_mul_2pi * 2.0 * std::f64::consts::PI / 365.2422,
+            c,
+            d,
+        })
+    };
+    let (noon, length) = (sine(0.25, 155.0, 12.4, 2.0), sine(2.1, -84.0, 12.2, 1.0));
+    let [rise, set] = Joint::NoonLength
+        .derived()
+        .map(|quantity| JointFitment::new(quantity, noon.clone(), length.clone()));
+    let (rise, set) = (Fitment::Joint(rise), Fitment::Joint(set));
+    let [joint_noon, joint_length] = Joint::RiseSet
+        .derived()
+        .map(|quantity| JointFitment::new(quantity, rise.clone(), set.clone()));
+
+    for x in (0..365).step_by(7).map(f64::from) {
+        assert!((set.eval(x) - rise.eval(x) - length.eval(x)).abs() < 1e-12);
+        assert!(((rise.eval(x) + set.eval(x)) / 2.0 - noon.eval(x)).abs() < 1e-12);
+        assert!((joint_noon.eval(x) - noon.eval(x)).abs() < 1e-12);
+        assert!((joint_length.eval(x) - length.eval(x)).abs() < 1e-12);
+    }
+    assert_eq!(Joint::RiseSet.fitted(), Joint::NoonLength.derived());
+}
@@ -0,0 +1,9 @@
+use sun::format::clock;
+
+#[test]
+fn clock_rounds_to_the_minute_and_keeps_the_sign() {
+    assert_eq!(clock(7.95), "07:57");
+    assert_eq!(clock(13.999), "14:00");
+    assert_eq!(clock(-0.5), "-00:30");
+    assert_eq!(clock(-1.25), "-01:15");
+}
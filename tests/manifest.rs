@@ -0,0 +1,46 @@
+use std::path::Path;
+
+use sun::manifest::{self, ManifestEntry};
+
+/// Entries of a CSV manifest with `rows` under the header.
+fn parse(rows: &str) -> Result<Vec<ManifestEntry>, String> {
+    let input = format!("label,location,start,end,output\n{}", rows);
+    manifest::parse(Path::new("locations.csv"), &input)
+}
+
+#[test]
+fn names_outputs_after_the_labels() {
+    let entries =
+        parse("\"Seattle, WA\",usa/seattle,,,\nTucson,Tucson AZ,2023-01-01,,tucson-2023.json\n")
+            .unwrap();
+
+    assert_eq!(entries[0].output, "seattle-wa.json");
+    assert_eq!(entries[1].output, "tucson-2023.json");
+    assert_eq!(entries[1].location, "Tucson AZ");
+}
+
+#[test]
+fn rejects_colliding_outputs() {
+    for rows in [
+        "Seattle,usa/seattle,,,\nseattle,179,,,\n",
+        "Seattle,usa/seattle,,,a.json\nTucson,usa/tucson,,,a.json\n",
+        "Index,usa/seattle,,,\n",
+        "***,usa/seattle,,,\n",
+        "Seattle,usa/seattle,,,a.json\nTucson,usa/tucson,,,A.json\n",
+    ] {
+        assert!(parse(rows).is_err(), "{}", rows);
+    }
+}
+
+#[test]
+fn keeps_outputs_in_the_output_directory() {
+    for rows in [
+        "Seattle,usa/seattle,,,../seattle.json\n",
+        "Seattle,usa/seattle,,,/tmp/seattle.json\n",
+        "Seattle,usa/seattle,,,wa/seattle.json\n",
+        "Seattle,usa/seattle,,,./seattle.json\n",
+        "Seattle,usa/seattle,,,..\n",
+    ] {
+        assert!(parse(rows).is_err(), "{}", rows);
+    }
+}
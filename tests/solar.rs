@@ -0,0 +1,98 @@
+use sun::solar;
+
+/// Julian day of a UTC instant.
+fn instant(y: u64, m: u64, d: u64, hour: u64, minute: u64) -> f64 {
+    solar::julian_day(y, m, d) + (hour as f64 + minute as f64 / 60.0) / 24.0
+}
+
+/// Hours from "hh:mm".
+fn hours(clock: &str) -> f64 {
+    let (hour, minute) = clock.split_once(':').unwrap();
+    hour.parse::<f64>().unwrap() + minute.parse::<f64>().unwrap() / 60.0
+}
+
+#[test]
+fn declination_matches_the_published_2024_seasons() {
+    // USNO: equinoxes 20 March 03:06 and 22 September 12:44 UTC
+    for (m, d, hour, minute, rising) in [(3, 20, 3, 6, true), (9, 22, 12, 44, false)] {
+        let at = instant(2024, m, d, hour, minute);
+        let (before, after) = (
+            solar::position(at - 1.0 / 24.0).declination,
+            solar::position(at + 1.0 / 24.0).declination,
+        );
+        assert_eq!(before < 0.0, rising, "{} {}", before, after);
+        assert_eq!(after > 0.0, rising, "{} {}", before, after);
+    }
+    // solstices 20 June 20:51 and 21 December 09:20 UTC, at the obliquity
+    for (m, d, hour, minute, sign) in [(6, 20, 20, 51, 1.0), (12, 21, 9, 20, -1.0)] {
+        let at = instant(2024, m, d, hour, minute);
+        let peak = solar::position(at).declination;
+        assert!((peak - sign * 23.436).abs() < 0.01, "{}", peak);
+        for offset in [-1.0, 1.0] {
+            let beside = solar::position(at + offset).declination;
+            assert!(sign * (peak - beside) > 0.0, "{} {}", peak, beside);
+        }
+    }
+}
+
+#[test]
+fn equation_of_time_matches_the_published_extremes() {
+    // NOAA: about -14.2 min on 11 February and +16.4 min on 3 November
+    for (m, d, minutes) in [(2, 11, -14.2), (11, 3, 16.4)] {
+        let eot = |offset: f64| solar::position(instant(2024, m, d, 12, 0) + offset).eot_minutes;
+        assert!((eot(0.0) - minutes).abs() < 0.1, "{}", eot(0.0));
+        for offset in [-3.0, 3.0] {
+            assert!(
+                eot(0.0).abs() > eot(offset).abs(),
+                "{} {}",
+                eot(0.0),
+                eot(offset)
+            );
+        }
+    }
+    // and near zero on 15 April, 13 June, 1 September and 25 December
+    for (m, d) in [(4, 15), (6, 13), (9, 1), (12, 25)] {
+        let eot = solar::position(instant(2024, m, d, 12, 0)).eot_minutes;
+        assert!(eot.abs() < 0.5, "{}-{}: {}", m, d, eot);
+    }
+}
+
+#[test]
+fn times_match_published_sunrise_and_sunset() {
+    // timeanddate.com, rounded to the minute
+    for (place, (y, m, d), (latitude, longitude, utc_offset), published) in [
+        (
+            "Seattle",
+            (2024, 6, 22),
+            (47.61, -122.33, -7.0),
+            ["05:12", "13:11", "21:11"],
+        ),
+        (
+            "Seattle",
+            (2024, 12, 24),
+            (47.61, -122.33, -8.0),
+            ["07:56", "12:09", "16:22"],
+        ),
+        (
+            "Tucson",
+            (2023, 1, 1),
+            (32.22, -110.97, -7.0),
+            ["07:24", "12:27", "17:29"],
+        ),
+    ] {
+        let sun = solar::solar_day(y, m, d, latitude, longitude, utc_offset);
+        let computed = [sun.sunrise.unwrap(), sun.noon, sun.sunset.unwrap()];
+        for (computed, published) in computed.iter().zip(published) {
+            assert!(
+                (computed - hours(published)).abs() * 60.0 < 1.5,
+                "{} {}-{}-{}: {:.3} vs {}",
+                place,
+                y,
+                m,
+                d,
+                computed,
+                published
+            );
+        }
+    }
+}
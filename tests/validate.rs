@@ -0,0 +1,90 @@
+use sun::validate::{Report, Severity};
+
+fn check(name: &str, contents: &str) -> Report {
+    let path = std::env::temp_dir().join(format!("sun-{}-{}.json", name, std::process::id()));
+    std::fs::write(&path, contents).unwrap();
+    let report = Report::check(&path);
+    std::fs::remove_file(&path).unwrap();
+    report
+}
+
+#[test]
+fn reports_bad_records_by_line() {
+    let report = check(
+        "bad",
+        r#"{
+  "location": {"country": "United States", "state": "Washington", "city": "Seattle",
+               "lat": 47.6, "lon": -122.3, "lat_dir": "N", "lon_dir": "W"},
+  "days": [
+    {"y": 2024, "m": 1, "d": 3, "yday": 3, "srise": "7:57", "sset": "16:30", "daylen": "8:33"},
+    {"y": 2024, "m": 1, "d": 21, "yday": 12, "srise": 7.75, "sset": 16.9},
+    {"y": 2024, "m": 1, "d": 3, "srise": 7.95, "sset": 16.5},
+    {"y": 2024, "m": 2, "d": 30, "srise": 7.2, "sset": 17.5},
+    {"y": 2024, "m": 3, "d": 1, "srise": 18.0, "sset": 6.5},
+    {"y": 2024, "m": 3, "d": 2, "srise": 6.8, "sset": 17.9, "daylen": 12.0},
+    {"y": 2024, "m": 3, "d": 3, "srise": "6:xx", "sset": 25.0},
+    {"m": 3, "d": 4, "srise": 6.7, "sset": 18.0},
+    {"y": 2024, "m": 3, "d": 5, "srise": 6.6}
+  ]
+}
+"#,
+    );
+
+    let errors: Vec<(usize, &str)> = report
+        .issues
+        .iter()
+        .filter(|issue| issue.severity == Severity::Error)
+        .map(|issue| (issue.line, issue.message.as_str()))
+        .collect();
+    assert_eq!(
+        errors,
+        [
+            (6, "yday 12, but 21 by the calendar"),
+            (7, "duplicate of line 5"),
+            (8, "not a calendar date"),
+            (9, "sunrise 18:00 is not before sunset 06:30"),
+            (10, "day length 12:00, but sunset - sunrise is 11:06"),
+            (11, "Sunrise \"6:xx\" is not a number"),
+            (11, "Sunset 25 is out of range"),
+            (12, "missing field `y`"),
+        ]
+    );
+    assert_eq!(report.errors, 8);
+    assert!(report.issues.iter().any(|issue| issue.line == 7
+        && issue.severity == Severity::Warning
+        && issue.message == "out of order, after 2024-01-21"));
+    assert!(report.issues.iter().any(|issue| issue.line == 13
+        && issue.date.as_deref() == Some("2024-03-05")
+        && issue.message == "no sunset"));
+}
+
+#[test]
+fn accepts_the_sample_data() {
+    for input in ["data/seattle.json", "data/tucson.json"] {
+        let report = Report::check(std::path::Path::new(input));
+
+        assert_eq!(report.errors + report.warnings, 0, "{}", report);
+        assert!(report.records > 20);
+    }
+}
+
+#[test]
+fn reports_json_syntax_errors() {
+    let report = check("syntax", "[\n  {\"y\": 2024,\n  \"m\": 1\n");
+
+    assert_eq!(report.errors, 1);
+    assert_eq!(report.issues[0].line, 4);
+}
+
+#[test]
+fn moon_records_need_no_sun_times() {
+    let report = check(
+        "moon",
+        r#"[
+  {"y": 2024, "m": 1, "d": 3, "mrise": 0.9, "mset": 12.1, "mphase": "waninggibbous", "millum": 55},
+  {"y": 2024, "m": 1, "d": 4, "mset": 12.5, "mtransit": 6.8, "millum": 45}
+]"#,
+    );
+
+    assert_eq!(report.errors + report.warnings, 0, "{}", report);
+}